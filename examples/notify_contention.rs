@@ -0,0 +1,83 @@
+//! Multi-producer notify throughput, compact vs cache-line padded pending bitmap.
+//!
+//!     cargo run --release --example notify_contention [producers] [seconds]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ipcbus::{Bus, BusConfig, SubscriptionMask};
+
+fn run(producers: usize, secs: u64, padded: bool) -> f64 {
+    let name = format!("ipcbus-contention-{}-{}", std::process::id(), padded);
+    let bus = Bus::open_or_create_with(&name, &BusConfig::new().pad_pending(padded));
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let start = Arc::new(Barrier::new(producers + 1));
+
+    let handles: Vec<_> = (0..producers)
+        .map(|p| {
+            let (name, stop, start) = (name.clone(), stop.clone(), start.clone());
+            thread::spawn(move || {
+                let bus = Bus::open_or_create(&name);
+                // One topic per producer, each in a different pending word.
+                let topic = p * 64;
+                let mut count = 0u64;
+                start.wait();
+                while !stop.load(Ordering::Relaxed) {
+                    bus.notify(topic);
+                    count += 1;
+                }
+                count
+            })
+        })
+        .collect();
+
+    // A consumer keeps clearing the bits so producers see real 0 -> 1 transitions.
+    let drainer = {
+        let (name, stop) = (name.clone(), stop.clone());
+        thread::spawn(move || {
+            let bus = Bus::open_or_create(&name);
+            let mask = SubscriptionMask::all();
+            while !stop.load(Ordering::Relaxed) {
+                bus.drain_pending_mask(&mask, |_| {});
+                while bus.try_wait() {}
+            }
+        })
+    };
+
+    start.wait();
+    let t0 = Instant::now();
+    thread::sleep(Duration::from_secs(secs));
+    stop.store(true, Ordering::Relaxed);
+    let total: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+    let elapsed = t0.elapsed().as_secs_f64();
+    drainer.join().unwrap();
+
+    drop(bus);
+    #[cfg(unix)]
+    {
+        let sem_name = std::ffi::CString::new(format!("/{}-notify", name)).unwrap();
+        unsafe {
+            libc::sem_unlink(sem_name.as_ptr());
+        }
+    }
+    total as f64 / elapsed
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let producers: usize = args.get(1).and_then(|a| a.parse().ok()).unwrap_or(5).min(ipcbus::BITWORDS);
+    let secs: u64 = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(2);
+
+    for padded in [false, true] {
+        let rate = run(producers, secs, padded);
+        println!(
+            "{:>8} layout: {} producers, {:.1} M notifies/s",
+            if padded { "padded" } else { "compact" },
+            producers,
+            rate / 1e6
+        );
+    }
+}
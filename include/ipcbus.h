@@ -0,0 +1,148 @@
+#ifndef IPCBUS_H
+#define IPCBUS_H
+
+/* Generated by cbindgen from src/cbus.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct Bus Bus;
+
+#define NUM_EXCHANGES 5
+
+#define NUM_MARKETS 2
+
+#define NUM_SYMBOLS 30
+
+#define NUM_CHANNELS 2
+
+#define NUM_TOPICS (((NUM_EXCHANGES * NUM_MARKETS) * NUM_SYMBOLS) * NUM_CHANNELS)
+
+/**
+ * Default ring depth; see `BusConfig::channel_slots`.
+ */
+#define SLOTS_PER_TOPIC 10
+
+/**
+ * Largest `BusConfig::payload_align`: a page, the alignment of the mapping itself.
+ */
+#define MAX_PAYLOAD_ALIGN 4096
+
+/**
+ * Deepest ring a channel can be configured with.
+ */
+#define MAX_SLOTS_PER_TOPIC (1 << 16)
+
+/**
+ * Default payload capacity of a slot; see `BusConfig::channel_data_size`.
+ */
+#define DATA_SIZE 1024
+
+/**
+ * Largest payload capacity a channel can be configured with, and the size of `Message::data`.
+ */
+#define MAX_DATA_SIZE 4096
+
+/**
+ * Sequence number reserved as the "empty" sentinel: a freshly zeroed slot has seq 0,
+ * so producers start at 1 and never publish seq 0. `read` never returns it.
+ */
+#define EMPTY_SEQ 0
+
+/**
+ * How many handles can be registered as consumers at once.
+ */
+#define MAX_CONSUMERS 8
+
+#define BUS_OK 0
+
+/**
+ * The bus pointer (or a required argument pointer) was null.
+ */
+#define BUS_ERR_NULL -1
+
+/**
+ * `topic` is not below the bus's `num_topics`.
+ */
+#define BUS_ERR_TOPIC -2
+
+/**
+ * Payload longer than the slot capacity; nothing was written.
+ */
+#define BUS_ERR_TOO_LARGE -3
+
+/**
+ * `seq` 0 is reserved as the empty sentinel.
+ */
+#define BUS_ERR_SEQ -4
+
+/**
+ * Notification groups per bus, including the default group 0.
+ */
+#define NUM_GROUPS 4
+
+/**
+ * Bumped whenever the in-segment layout changes incompatibly.
+ */
+#define LAYOUT_VERSION 23
+
+/**
+ * Pending words are spaced one cache line apart.
+ */
+#define FLAG_PADDED_PENDING (1 << 0)
+
+/**
+ * Longest topic name, in bytes.
+ */
+#define MAX_TOPIC_NAME_LEN 64
+
+/**
+ * How many times a reader retries a slot that is mid-write before giving up.
+ */
+#define SEQLOCK_RETRIES 64
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Open (or create) the bus `name`. Returns null on failure.
+ * The handle must be released with `bus_free`.
+ *
+ * # Safety
+ * `name` must be null or a valid NUL-terminated string.
+ */
+Bus *bus_open_or_create(const char *name);
+
+/**
+ * Store `len` bytes at `data` as message `seq` on `topic`. Does not notify.
+ *
+ * # Safety
+ * `bus` must come from `bus_open_or_create`; `data` must point to `len` readable bytes.
+ */
+int32_t bus_write(Bus *bus, size_t topic, uint64_t seq, const uint8_t *data, size_t len);
+
+/**
+ * Mark `topic` pending and wake consumers.
+ *
+ * # Safety
+ * `bus` must come from `bus_open_or_create`.
+ */
+int32_t bus_notify(Bus *bus, size_t topic);
+
+/**
+ * Release a handle from `bus_open_or_create`. Null is a no-op.
+ *
+ * # Safety
+ * `bus` must be null or a live handle that is not used afterwards.
+ */
+void bus_free(Bus *bus);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* IPCBUS_H */
@@ -0,0 +1,124 @@
+//! Consumer acknowledgements, so producers can see how far behind the slowest
+//! reader is before the ring laps it.
+//!
+//! A `Bus` handle that calls `register_consumer` claims one of MAX_CONSUMERS rows
+//! in the journal and publishes the highest seq it has processed per topic there.
+//! Rows are independent and only move forward, so the minimum across claimed rows
+//! is a reclaim watermark: every consumer is done with any seq at or below it.
+
+use core::sync::atomic::Ordering;
+
+use crate::{as_atomic, next_seq, seq_newer, BusError, SizedBus, EMPTY_SEQ, NUM_TOPICS};
+
+/// How many handles can be registered as consumers at once.
+pub const MAX_CONSUMERS: usize = 8;
+
+/// `Bus::consumer` value of a handle that hasn't registered.
+pub(crate) const NO_CONSUMER: usize = usize::MAX;
+
+/// `seq_newer` for ack rows, where `EMPTY_SEQ` (nothing acked yet) is older than any seq.
+#[inline]
+pub(crate) fn ack_newer(a: u64, b: u64) -> bool {
+    a != EMPTY_SEQ && (b == EMPTY_SEQ || seq_newer(a, b))
+}
+
+/// Per-consumer ack rows. `owner[c]` is nonzero (the owner's pid) while row `c` is
+/// claimed; a process that dies without dropping its Bus keeps its row, and its lag.
+#[repr(C)]
+pub struct Acks<const TOPICS: usize = NUM_TOPICS> {
+    pub owner: [u64; MAX_CONSUMERS],
+    pub seq: [[u64; TOPICS]; MAX_CONSUMERS],
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    #[inline]
+    fn acks(&self) -> &Acks<TOPICS> {
+        unsafe { &(*self.journal).acks }
+    }
+
+    /// Claim a consumer row for this handle, with every topic acked up to its current
+    /// latest seq (history from before registration doesn't count as lag). Returns the
+    /// row index; calling it again on a registered handle returns the same row.
+    pub fn register_consumer(&self) -> Result<usize, BusError> {
+        let current = self.consumer.load(Ordering::Acquire);
+        if current != NO_CONSUMER {
+            return Ok(current);
+        }
+        let acks = self.acks();
+        let pid = u64::from(std::process::id()).max(1);
+        for c in 0..MAX_CONSUMERS {
+            if unsafe { as_atomic(&acks.owner[c]) }
+                .compare_exchange(0, pid, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                for t in 0..self.num_topics() {
+                    unsafe { as_atomic(&acks.seq[c][t]) }.store(self.get_latest_seq(t), Ordering::Release);
+                }
+                self.consumer.store(c, Ordering::Release);
+                return Ok(c);
+            }
+        }
+        Err(BusError::TooManyConsumers { max: MAX_CONSUMERS })
+    }
+
+    /// Release this handle's consumer row (also done when the Bus is dropped).
+    pub fn unregister_consumer(&self) {
+        let c = self.consumer.swap(NO_CONSUMER, Ordering::AcqRel);
+        if c != NO_CONSUMER {
+            unsafe { as_atomic(&self.acks().owner[c]) }.store(0, Ordering::Release);
+        }
+    }
+
+    /// Consumer: record that everything up to `seq` on `topic_id` has been processed.
+    /// Acks never move backwards (by `seq_newer`), so a late or duplicate ack is harmless.
+    /// Does nothing if this handle isn't registered as a consumer.
+    #[inline]
+    pub fn ack(&self, topic_id: usize, seq: u64) {
+        let c = self.consumer.load(Ordering::Relaxed);
+        debug_assert!(c != NO_CONSUMER, "ack on a handle that never called register_consumer");
+        if c != NO_CONSUMER {
+            let row = unsafe { as_atomic(&self.acks().seq[c][topic_id]) };
+            let _ = row.fetch_update(Ordering::Release, Ordering::Relaxed, |acked| ack_newer(seq, acked).then_some(seq));
+        }
+    }
+
+    /// Lowest ack on `topic_id` across registered consumers, or None if there are none.
+    pub(crate) fn min_ack(&self, topic_id: usize) -> Option<u64> {
+        let acks = self.acks();
+        (0..MAX_CONSUMERS)
+            .filter(|&c| unsafe { as_atomic(&acks.owner[c]) }.load(Ordering::Acquire) != 0)
+            .map(|c| unsafe { as_atomic(&acks.seq[c][topic_id]) }.load(Ordering::Acquire))
+            .reduce(|a, b| if ack_newer(a, b) { b } else { a })
+    }
+
+    /// Highest seq on `topic_id` that every registered consumer has acked, or None
+    /// with no consumers registered. Slots holding seqs at or below it are safe to reuse.
+    pub fn reclaim_watermark(&self, topic_id: usize) -> Option<u64> {
+        self.min_ack(topic_id)
+    }
+
+    /// Producer: the first seq on `topic_id` some registered consumer hasn't acked, or
+    /// None if everything published so far is acked (or nobody is registered). Writing
+    /// seq `s >= oldest_unacked + depth(topic_id)` would overwrite it.
+    pub fn oldest_unacked(&self, topic_id: usize) -> Option<u64> {
+        let next = next_seq(self.min_ack(topic_id)?);
+        let latest = self.get_latest_seq(topic_id);
+        (latest != EMPTY_SEQ && !ack_newer(next, latest)).then_some(next)
+    }
+
+    /// Producer: how many published seqs the slowest registered consumer hasn't acked
+    /// yet (`latest_seq - min ack`, across the wrap), or 0 with no consumers. Once it
+    /// reaches `depth(topic_id)`, the next write overwrites a message someone hasn't processed.
+    pub fn topic_lag(&self, topic_id: usize) -> u64 {
+        let Some(ack) = self.min_ack(topic_id) else { return 0 };
+        let latest = self.get_latest_seq(topic_id);
+        if !ack_newer(latest, ack) {
+            return 0;
+        }
+        // Numbering skips EMPTY_SEQ when it wraps, so that one isn't a lagging seq.
+        let lag = latest.wrapping_sub(ack);
+        if latest < ack { lag - 1 } else { lag }
+    }
+}
@@ -0,0 +1,506 @@
+//! Where the segment's memory comes from.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, Weak};
+use std::time::Duration;
+
+#[cfg(unix)]
+use std::os::fd::AsRawFd;
+
+use memmap2::{Mmap, MmapMut};
+use shared_memory::{Shmem, ShmemConf, ShmemError};
+
+use crate::{BusError, JournalHeader, OpenMode, BITWORDS};
+
+/// Memory backing a bus. Both give every process the same `Journal` layout; pick
+/// `File` where POSIX shared memory is blocked (e.g. some sandboxed containers).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Backing {
+    /// A POSIX shared memory object (a file mapping on Windows) named after the bus.
+    #[default]
+    Shm,
+    /// A regular file mapped shared, ideally on a tmpfs. It is created if missing and
+    /// left in place when the bus is dropped.
+    File(PathBuf),
+    /// A heap allocation shared by every handle with the same name in this process,
+    /// woken through a Condvar instead of a named semaphore: for tests that shouldn't
+    /// touch the OS. Read-only opens aren't actually protected.
+    #[cfg(feature = "testing")]
+    Heap,
+}
+
+/// OS error `shared_memory` reports when opening a name that doesn't exist.
+#[cfg(unix)]
+const NOT_FOUND: u32 = libc::ENOENT as u32;
+#[cfg(windows)]
+const NOT_FOUND: u32 = windows_sys::Win32::Foundation::ERROR_FILE_NOT_FOUND;
+
+/// An open mapping of the segment.
+pub(crate) enum Mapping {
+    Shm(Shmem),
+    File(MmapMut),
+    /// `PROT_READ` mapping of a shm object; writing through it faults.
+    #[cfg(unix)]
+    ReadOnlyShm(ReadOnlyShm),
+    ReadOnlyFile(Mmap),
+    /// Read-write mapping at `BusConfig::fixed_addr`.
+    #[cfg(unix)]
+    Fixed(FixedMap),
+    #[cfg(feature = "testing")]
+    Heap(std::sync::Arc<crate::heap::HeapSegment>),
+}
+
+impl Mapping {
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        match self {
+            Mapping::Shm(shmem) => shmem.as_ptr(),
+            Mapping::File(map) => map.as_ptr() as *mut u8,
+            #[cfg(unix)]
+            Mapping::ReadOnlyShm(map) => map.ptr as *mut u8,
+            Mapping::ReadOnlyFile(map) => map.as_ptr() as *mut u8,
+            #[cfg(unix)]
+            Mapping::Fixed(map) => map.ptr as *mut u8,
+            #[cfg(feature = "testing")]
+            Mapping::Heap(segment) => segment.as_ptr(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Mapping::Shm(shmem) => shmem.len(),
+            Mapping::File(map) => map.len(),
+            #[cfg(unix)]
+            Mapping::ReadOnlyShm(map) => map.len,
+            Mapping::ReadOnlyFile(map) => map.len(),
+            #[cfg(unix)]
+            Mapping::Fixed(map) => map.len.load(Ordering::Acquire),
+            #[cfg(feature = "testing")]
+            Mapping::Heap(segment) => segment.len(),
+        }
+    }
+}
+
+#[cfg(unix)]
+pub(crate) struct ReadOnlyShm {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+#[cfg(unix)]
+impl Drop for ReadOnlyShm {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+#[cfg(unix)]
+pub(crate) struct FixedMap {
+    ptr: *mut libc::c_void,
+    /// Shrinks if `SharedMapping::remap` puts a smaller segment in its place.
+    len: AtomicUsize,
+}
+
+#[cfg(unix)]
+impl FixedMap {
+    /// Map `len` bytes of `file` read-write at exactly `addr`, never over another
+    /// mapping: `MAP_FIXED_NOREPLACE` on Linux, a plain hint elsewhere, and either
+    /// way the result is checked.
+    fn new(file: &File, len: usize, addr: usize) -> Result<Self, BusError> {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let flags = libc::MAP_SHARED | libc::MAP_FIXED_NOREPLACE;
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        let flags = libc::MAP_SHARED;
+        let prot = libc::PROT_READ | libc::PROT_WRITE;
+        let ptr = unsafe { libc::mmap(addr as *mut libc::c_void, len, prot, flags, file.as_raw_fd(), 0) };
+        if ptr == libc::MAP_FAILED {
+            let err = std::io::Error::last_os_error();
+            return Err(match err.raw_os_error() {
+                Some(libc::EEXIST) => BusError::Open(format!("{:#x}: address range already mapped", addr)),
+                _ => BusError::Open(format!("mmap at {:#x}: {}", addr, err)),
+            });
+        }
+        if ptr as usize != addr {
+            // An older kernel (or another unix) took the address as a hint only.
+            unsafe { libc::munmap(ptr, len) };
+            return Err(BusError::Open(format!("{:#x}: address range unavailable", addr)));
+        }
+        Ok(Self { ptr, len: AtomicUsize::new(len) })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for FixedMap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, *self.len.get_mut());
+        }
+    }
+}
+
+/// A read-write mapping shared by every handle on one bus in this process.
+pub(crate) struct SharedMapping {
+    mapping: Mapping,
+    /// Name and backing of a shm object this mapping created. `Shmem` would unlink
+    /// it on drop even after it was replaced; this unlinks only while it is current.
+    owner: Mutex<Option<(Backing, String)>>,
+    /// Bit per topic with a `WriteGuard` open on some handle of this mapping.
+    guarded: [AtomicU64; BITWORDS],
+}
+
+// Whoever holds it touches the segment only through atomics and the seqlock, as
+// separate processes do.
+unsafe impl Send for SharedMapping {}
+unsafe impl Sync for SharedMapping {}
+
+impl SharedMapping {
+    /// Wrap a mapping no other handle will share (e.g. a read-only one).
+    pub(crate) fn private(mapping: Mapping) -> Arc<Self> {
+        Arc::new(Self { mapping, owner: Mutex::new(None), guarded: Default::default() })
+    }
+
+    /// Mark `topic_id` as having a `WriteGuard` open; false if one already is.
+    pub(crate) fn claim_guard(&self, topic_id: usize) -> bool {
+        let bit = 1u64 << (topic_id % 64);
+        self.guarded[topic_id / 64].fetch_or(bit, Ordering::Acquire) & bit == 0
+    }
+
+    /// Undo `claim_guard` once the guard is gone.
+    pub(crate) fn release_guard(&self, topic_id: usize) {
+        self.guarded[topic_id / 64].fetch_and(!(1u64 << (topic_id % 64)), Ordering::Release);
+    }
+
+    /// Map the segment now under `name` over this fixed-address mapping's pages, so
+    /// pointers into it stay valid across `Bus::reopen`. Fails, leaving the pages
+    /// alone, if the new segment doesn't fit in the range. The mapping then becomes
+    /// the one this process shares for the name.
+    #[cfg(unix)]
+    pub(crate) fn remap(self: &Arc<Self>, name: &str, backing: &Backing) -> Result<(), BusError> {
+        let Mapping::Fixed(map) = &self.mapping else {
+            return Err(BusError::Open("only fixed-address mappings are remapped in place".into()));
+        };
+        let mut shared = shared();
+        let (file, _) = open_fd(name, backing, 0, false)?;
+        let len = file.metadata().map_err(|e| BusError::Open(e.to_string()))?.len() as usize;
+        let old_len = map.len.load(Ordering::Acquire);
+        if len > old_len {
+            return Err(BusError::Open(format!(
+                "new segment of {} bytes doesn't fit the {} mapped at {:p}",
+                len, old_len, map.ptr
+            )));
+        }
+        let prot = libc::PROT_READ | libc::PROT_WRITE;
+        let flags = libc::MAP_SHARED | libc::MAP_FIXED;
+        let ptr = unsafe { libc::mmap(map.ptr, len, prot, flags, file.as_raw_fd(), 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(BusError::Open(format!("mmap at {:p}: {}", map.ptr, std::io::Error::last_os_error())));
+        }
+        // Pages past the new end still map the old segment.
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let (end, old_end) = (len.div_ceil(page) * page, old_len.div_ceil(page) * page);
+        if old_end > end {
+            unsafe { libc::munmap(map.ptr.cast::<u8>().add(end).cast(), old_end - end) };
+        }
+        map.len.store(len, Ordering::Release);
+        // The segment this mapping created is gone; its successor isn't ours to unlink.
+        *self.owner.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        shared.insert((backing.clone(), name.to_owned()), Arc::downgrade(self));
+        Ok(())
+    }
+}
+
+impl Drop for SharedMapping {
+    fn drop(&mut self) {
+        let owner = self.owner.get_mut().unwrap_or_else(|e| e.into_inner());
+        if let Some((backing, name)) = owner
+            && is_current(name, backing, &self.mapping)
+        {
+            unlink(name, backing);
+        }
+    }
+}
+
+impl Deref for SharedMapping {
+    type Target = Mapping;
+
+    fn deref(&self) -> &Mapping {
+        &self.mapping
+    }
+}
+
+/// Read-write mappings open in this process, by backing and name.
+type SharedTable = HashMap<(Backing, String), Weak<SharedMapping>>;
+
+fn shared() -> MutexGuard<'static, SharedTable> {
+    static SHARED: OnceLock<Mutex<SharedTable>> = OnceLock::new();
+    SHARED.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// `map` for a read-write handle: reuses the mapping another handle in this process
+/// already has, as long as it is still the segment under the name, so the journal
+/// is mapped once and a shm owner unlinks only when the last handle drops. A
+/// segment that was unlinked or replaced since gets a fresh mapping, which takes
+/// over the name's entry. With `fixed_addr`, the mapping has to be at that address.
+/// Returns the mapping and whether this call created the segment.
+pub(crate) fn map_shared(
+    name: &str,
+    backing: &Backing,
+    size: usize,
+    mode: OpenMode,
+    fixed_addr: Option<usize>,
+) -> Result<(Arc<SharedMapping>, bool), BusError> {
+    let mut shared = shared();
+    let key = (backing.clone(), name.to_owned());
+    if let Some(mapping) = shared.get(&key).and_then(Weak::upgrade)
+        && is_current(name, backing, &mapping)
+    {
+        if matches!(mode, OpenMode::Exclusive) {
+            return Err(BusError::AlreadyExists);
+        }
+        if let Some(addr) = fixed_addr
+            && mapping.as_ptr() as usize != addr
+        {
+            return Err(BusError::Open(format!("{:#x}: bus already mapped at {:p} in this process", addr, mapping.as_ptr())));
+        }
+        return Ok((mapping, false));
+    }
+    let map = |create| match fixed_addr {
+        Some(addr) => map_fixed(name, backing, size, create, addr),
+        None => map(name, backing, size, create),
+    };
+    let (mapping, created) = match mode {
+        OpenMode::Create => map(true)?,
+        OpenMode::Attach | OpenMode::ReadOnly => map(false)?,
+        OpenMode::Exclusive => match map(true)? {
+            (_, false) => return Err(BusError::AlreadyExists),
+            created => created,
+        },
+    };
+    #[cfg(unix)]
+    let mut mapping = mapping;
+    #[cfg(unix)]
+    let owner = match &mut mapping {
+        Mapping::Shm(shmem) => shmem.set_owner(false).then(|| key.clone()),
+        Mapping::Fixed(_) => (created && *backing == Backing::Shm).then(|| key.clone()),
+        _ => None,
+    };
+    #[cfg(not(unix))]
+    let owner = None;
+    let mapping = Arc::new(SharedMapping { mapping, owner: Mutex::new(owner), guarded: Default::default() });
+    shared.retain(|_, m| m.strong_count() > 0);
+    shared.insert(key, Arc::downgrade(&mapping));
+    Ok((mapping, created))
+}
+
+/// Whether `mapping` still maps the segment under `name`. Where that can't be
+/// checked read-only (Windows shm, whose name lives as long as any handle to
+/// it), the answer is yes.
+fn is_current(name: &str, backing: &Backing, mapping: &Mapping) -> bool {
+    let generation = |m: &Mapping| unsafe { &*(m.as_ptr() as *const JournalHeader) }.generation();
+    match map_readonly(name, backing) {
+        Ok(current) => current.len() >= core::mem::size_of::<JournalHeader>() && generation(&current) == generation(mapping),
+        Err(BusError::NotFound) => false,
+        Err(_) => true,
+    }
+}
+
+/// Remove the segment's name (the shm object or the file); mappings stay valid
+/// until unmapped. A missing name is not an error. Windows file mappings are
+/// freed with their last handle, so there's nothing to remove there.
+pub(crate) fn unlink(name: &str, backing: &Backing) {
+    match backing {
+        #[cfg(unix)]
+        Backing::Shm => {
+            if let Ok(c_name) = std::ffi::CString::new(name) {
+                unsafe {
+                    libc::shm_unlink(c_name.as_ptr());
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        Backing::Shm => {
+            let _ = name;
+        }
+        Backing::File(path) => {
+            let _ = std::fs::remove_file(path);
+        }
+        #[cfg(feature = "testing")]
+        Backing::Heap => crate::heap::unlink(name),
+    }
+}
+
+/// Map an existing segment read-only.
+pub(crate) fn map_readonly(name: &str, backing: &Backing) -> Result<Mapping, BusError> {
+    match backing {
+        #[cfg(unix)]
+        Backing::Shm => map_shm_readonly(name),
+        #[cfg(not(unix))]
+        Backing::Shm => {
+            let _ = name;
+            Err(BusError::Open("read-only shared memory mappings are only supported on unix".into()))
+        }
+        Backing::File(path) => {
+            let open_err = |e: std::io::Error| BusError::Open(format!("{}: {}", path.display(), e));
+            let file = OpenOptions::new().read(true).open(path).map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => BusError::NotFound,
+                _ => open_err(e),
+            })?;
+            let map = unsafe { Mmap::map(&file) }.map_err(open_err)?;
+            Ok(Mapping::ReadOnlyFile(map))
+        }
+        #[cfg(feature = "testing")]
+        Backing::Heap => crate::heap::map(name, 0, false).map(|(segment, _)| Mapping::Heap(segment)),
+    }
+}
+
+/// Same object `ShmemConf::os_id(name)` opens, mapped PROT_READ.
+#[cfg(unix)]
+fn map_shm_readonly(name: &str) -> Result<Mapping, BusError> {
+    let os_err = |what: &str| BusError::Open(format!("{}({}): {}", what, name, std::io::Error::last_os_error()));
+    let c_name = std::ffi::CString::new(name).map_err(|e| BusError::Open(e.to_string()))?;
+    unsafe {
+        let fd = libc::shm_open(c_name.as_ptr(), libc::O_RDONLY, 0);
+        if fd < 0 {
+            return Err(match std::io::Error::last_os_error().raw_os_error() {
+                Some(libc::ENOENT) => BusError::NotFound,
+                _ => os_err("shm_open"),
+            });
+        }
+        let mut st: libc::stat = core::mem::zeroed();
+        if libc::fstat(fd, &mut st) != 0 {
+            let err = os_err("fstat");
+            libc::close(fd);
+            return Err(err);
+        }
+        let len = st.st_size as usize;
+        let ptr = libc::mmap(core::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_SHARED, fd, 0);
+        libc::close(fd);
+        if ptr == libc::MAP_FAILED {
+            return Err(os_err("mmap"));
+        }
+        Ok(Mapping::ReadOnlyShm(ReadOnlyShm { ptr, len }))
+    }
+}
+
+/// Create the segment with `size` bytes (if `create`), or open the existing one.
+/// Returns the mapping and whether this call created it.
+pub(crate) fn map(name: &str, backing: &Backing, size: usize, create: bool) -> Result<(Mapping, bool), BusError> {
+    match backing {
+        Backing::Shm => match create.then(|| ShmemConf::new().size(size).os_id(name).create()) {
+            Some(Ok(s)) => Ok((Mapping::Shm(s), true)),
+            _ => ShmemConf::new()
+                .size(size)
+                .os_id(name)
+                .open()
+                .map(|s| (Mapping::Shm(s), false))
+                .map_err(|e| match e {
+                    ShmemError::MapOpenFailed(code) if code == NOT_FOUND => BusError::NotFound,
+                    e => BusError::Open(e.to_string()),
+                }),
+        },
+        Backing::File(path) => map_file(path, size, create),
+        #[cfg(feature = "testing")]
+        Backing::Heap => crate::heap::map(name, size, create).map(|(segment, created)| (Mapping::Heap(segment), created)),
+    }
+}
+
+fn map_file(path: &Path, size: usize, create: bool) -> Result<(Mapping, bool), BusError> {
+    let (file, created) = open_file(path, size, create)?;
+    let map = unsafe { MmapMut::map_mut(&file) }.map_err(|e| BusError::Open(format!("{}: {}", path.display(), e)))?;
+    Ok((Mapping::File(map), created))
+}
+
+/// Create the file with `size` bytes (if `create`), or open the existing one once
+/// its creator has sized it.
+fn open_file(path: &Path, size: usize, create: bool) -> Result<(File, bool), BusError> {
+    let open_err = |e: std::io::Error| BusError::Open(format!("{}: {}", path.display(), e));
+
+    let (file, created) = match OpenOptions::new().read(true).write(true).create_new(create).open(path) {
+        Ok(file) if !create => (file, false),
+        Ok(file) => {
+            file.set_len(size as u64).map_err(open_err)?;
+            (file, true)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && !create => return Err(BusError::NotFound),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            let file = OpenOptions::new().read(true).write(true).open(path).map_err(open_err)?;
+            // The creator sizes the file right after creating it; give it a moment.
+            let mut waited = 0;
+            while file.metadata().map_err(open_err)?.len() == 0 && waited < 1000 {
+                std::thread::sleep(Duration::from_millis(1));
+                waited += 1;
+            }
+            (file, false)
+        }
+        Err(e) => return Err(open_err(e)),
+    };
+    Ok((file, created))
+}
+
+/// `map`, but at exactly `addr` (see `BusConfig::fixed_addr`).
+#[cfg(unix)]
+fn map_fixed(name: &str, backing: &Backing, size: usize, create: bool, addr: usize) -> Result<(Mapping, bool), BusError> {
+    let (file, created) = open_fd(name, backing, size, create)?;
+    let len = file.metadata().map_err(|e| BusError::Open(e.to_string()))?.len() as usize;
+    Ok((Mapping::Fixed(FixedMap::new(&file, len, addr)?), created))
+}
+
+#[cfg(not(unix))]
+fn map_fixed(_: &str, _: &Backing, _: usize, _: bool, _: usize) -> Result<(Mapping, bool), BusError> {
+    Err(BusError::Open("fixed_addr is only supported on unix".into()))
+}
+
+/// The segment's shm object or file, opened read-write (and created with `size`
+/// bytes if `create` and missing), for mapping it by hand.
+#[cfg(unix)]
+fn open_fd(name: &str, backing: &Backing, size: usize, create: bool) -> Result<(File, bool), BusError> {
+    match backing {
+        Backing::Shm => open_shm(name, size, create),
+        Backing::File(path) => open_file(path, size, create),
+        #[cfg(feature = "testing")]
+        Backing::Heap => Err(BusError::Open("heap segments can't be mapped at a fixed address".into())),
+    }
+}
+
+/// `open_file` for shm object `name`, with the mode `ShmemConf` creates it with.
+#[cfg(unix)]
+fn open_shm(name: &str, size: usize, create: bool) -> Result<(File, bool), BusError> {
+    use std::os::fd::FromRawFd;
+
+    let os_err = |what: &str| BusError::Open(format!("{}({}): {}", what, name, std::io::Error::last_os_error()));
+    let c_name = std::ffi::CString::new(name).map_err(|e| BusError::Open(e.to_string()))?;
+    let mode = (libc::S_IRUSR | libc::S_IWUSR) as libc::c_uint;
+    if create {
+        let fd = unsafe { libc::shm_open(c_name.as_ptr(), libc::O_RDWR | libc::O_CREAT | libc::O_EXCL, mode) };
+        if fd >= 0 {
+            let file = unsafe { File::from_raw_fd(fd) };
+            file.set_len(size as u64).map_err(|e| BusError::Open(format!("ftruncate({}): {}", name, e)))?;
+            return Ok((file, true));
+        }
+        if std::io::Error::last_os_error().raw_os_error() != Some(libc::EEXIST) {
+            return Err(os_err("shm_open"));
+        }
+    }
+    let fd = unsafe { libc::shm_open(c_name.as_ptr(), libc::O_RDWR, 0) };
+    if fd < 0 {
+        return Err(match std::io::Error::last_os_error().raw_os_error() {
+            Some(libc::ENOENT) => BusError::NotFound,
+            _ => os_err("shm_open"),
+        });
+    }
+    let file = unsafe { File::from_raw_fd(fd) };
+    // As with files, the creator sizes the object right after creating it.
+    let mut waited = 0;
+    while file.metadata().map_err(|e| BusError::Open(e.to_string()))?.len() == 0 && waited < 1000 {
+        std::thread::sleep(Duration::from_millis(1));
+        waited += 1;
+    }
+    Ok((file, false))
+}
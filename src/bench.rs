@@ -0,0 +1,101 @@
+//! Producer/consumer round-trip measurement used by the `benchmark` binary.
+
+use std::time::{Duration, Instant};
+
+use crate::{seq_newer, DrainOutcome, SizedBus, SubscriptionMask};
+
+/// Result of `Bus::throughput`.
+#[derive(Clone, Debug)]
+pub struct ThroughputReport {
+    pub payload_len: usize,
+    pub topics: usize,
+    /// Messages the producer published.
+    pub published: u64,
+    /// Messages the consumer saw. Lower than `published` when the consumer falls
+    /// behind and only the latest message of a pending topic is read.
+    pub received: u64,
+    pub elapsed: Duration,
+    /// Publish-to-read latency percentiles over the received messages.
+    pub p50: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl ThroughputReport {
+    pub fn published_per_sec(&self) -> f64 {
+        self.published as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn received_per_sec(&self) -> f64 {
+        self.received as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Self-test: publish `messages` payloads of `payload_len` bytes round-robin over
+    /// the first `topics` topics from a producer thread, while this thread consumes
+    /// them, and report throughput and end-to-end latency (from `publish_ts_nanos`).
+    ///
+    /// Run it on a bus nothing else is using: it consumes the bus's notifications.
+    pub fn throughput(&self, payload_len: usize, topics: usize, messages: u64) -> ThroughputReport {
+        let topics = topics.clamp(1, self.num_topics());
+        let capacity = (0..topics).map(|t| self.capacity(t)).min().unwrap_or(0);
+        let payload = vec![0xA5u8; payload_len.min(capacity)];
+        let mask = SubscriptionMask::from_topics(&(0..topics).collect::<Vec<_>>());
+        let first_seq: Vec<u64> = (0..topics).map(|t| self.get_latest_seq(t) + 1).collect();
+        let mut last_seen: Vec<u64> = first_seq.iter().map(|&s| s - 1).collect();
+        let last_seq: Vec<u64> = (0..topics)
+            .map(|t| first_seq[t] + messages / topics as u64 + u64::from((t as u64) < messages % topics as u64) - 1)
+            .collect();
+
+        let mut latencies = Vec::with_capacity(messages as usize);
+        let start = Instant::now();
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for i in 0..messages {
+                    let t = (i % topics as u64) as usize;
+                    let seq = first_seq[t] + i / topics as u64;
+                    if self.write(t, seq, &payload).is_ok() {
+                        self.notify(t);
+                    }
+                }
+            });
+
+            while (0..topics).any(|t| seq_newer(last_seq[t], last_seen[t])) {
+                let mut on_topic = |t: usize| {
+                    if let Some(msg) = self.read_latest(t)
+                        && seq_newer(msg.seq, last_seen[t])
+                    {
+                        latencies.push(self.now().saturating_sub(msg.publish_ts_nanos));
+                        last_seen[t] = msg.seq;
+                    }
+                };
+                // A timeout means a wakeup may have been missed; look anyway.
+                if self.wait_and_drain_mask_timeout(&mask, Duration::from_millis(10), &mut on_topic)
+                    == DrainOutcome::TimedOut
+                {
+                    self.drain_now(&mask, &mut on_topic);
+                }
+            }
+        });
+        let elapsed = start.elapsed();
+
+        latencies.sort_unstable();
+        let pct = |p: usize| {
+            let i = (latencies.len() * p / 100).min(latencies.len().saturating_sub(1));
+            Duration::from_nanos(latencies.get(i).copied().unwrap_or(0))
+        };
+        ThroughputReport {
+            payload_len: payload.len(),
+            topics,
+            published: messages,
+            received: latencies.len() as u64,
+            elapsed,
+            p50: pct(50),
+            p99: pct(99),
+            max: Duration::from_nanos(latencies.last().copied().unwrap_or(0)),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! End-to-end throughput and latency of a producer/consumer pair on a temp bus.
+//!
+//!     cargo run --release --bin benchmark [payload_bytes] [topics] [messages]
+
+use ipcbus::Bus;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let payload_len: usize = args.get(1).and_then(|a| a.parse().ok()).unwrap_or(64);
+    let topics: usize = args.get(2).and_then(|a| a.parse().ok()).unwrap_or(1);
+    let messages: u64 = args.get(3).and_then(|a| a.parse().ok()).unwrap_or(1_000_000);
+
+    let name = format!("ipcbus-bench-{}", std::process::id());
+    let bus = Bus::open_or_create(&name);
+    let report = bus.throughput(payload_len, topics, messages);
+    drop(bus);
+    #[cfg(unix)]
+    {
+        let sem_name = std::ffi::CString::new(format!("/{}-notify", name)).unwrap();
+        unsafe {
+            libc::sem_unlink(sem_name.as_ptr());
+        }
+    }
+
+    println!("payload      {} bytes over {} topic(s)", report.payload_len, report.topics);
+    println!("published    {} in {:?} ({:.2} M msg/s)", report.published, report.elapsed, report.published_per_sec() / 1e6);
+    println!("received     {} ({:.2} M msg/s, the rest were coalesced)", report.received, report.received_per_sec() / 1e6);
+    println!("latency      p50 {:?}  p99 {:?}  max {:?}", report.p50, report.p99, report.max);
+}
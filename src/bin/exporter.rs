@@ -0,0 +1,44 @@
+//! Serves a bus's counters for Prometheus to scrape, mapped read-only so it can
+//! sit next to a live producer and consumer without touching their state.
+//!
+//!     cargo run --release --bin exporter <bus_name> [listen_addr]
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+
+use ipcbus::Bus;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(name) = args.get(1) else {
+        eprintln!("Usage: {} <bus_name> [listen_addr (default 127.0.0.1:9464)]", args[0]);
+        std::process::exit(2);
+    };
+    let addr = args.get(2).map_or("127.0.0.1:9464", String::as_str);
+
+    let bus = Bus::open_readonly(name).unwrap_or_else(|e| {
+        eprintln!("[exporter] cannot open bus '{}': {}", name, e);
+        std::process::exit(1);
+    });
+    let listener = TcpListener::bind(addr).unwrap_or_else(|e| {
+        eprintln!("[exporter] cannot listen on {}: {}", addr, e);
+        std::process::exit(1);
+    });
+    println!("[exporter] bus='{}' serving metrics on http://{}/metrics", name, addr);
+
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue };
+        // Every path gets the metrics; only the request line matters.
+        let mut request_line = String::new();
+        if BufReader::new(&stream).read_line(&mut request_line).is_err() {
+            continue;
+        }
+        let body = bus.stats_prometheus();
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+    }
+}
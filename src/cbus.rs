@@ -0,0 +1,98 @@
+//! C ABI for non-Rust producers (e.g. a C++ gateway publishing into the same bus).
+//!
+//! The header lives in `include/ipcbus.h` and is generated with
+//! `cbindgen --config cbindgen.toml --output include/ipcbus.h`.
+//! All functions return `BUS_OK` (0) or a negative `BUS_ERR_*` code; none of
+//! them unwind into the caller.
+
+use std::ffi::{CStr, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use crate::{Bus, BusError, EMPTY_SEQ};
+
+pub const BUS_OK: i32 = 0;
+/// The bus pointer (or a required argument pointer) was null.
+pub const BUS_ERR_NULL: i32 = -1;
+/// `topic` is not below the bus's `num_topics`.
+pub const BUS_ERR_TOPIC: i32 = -2;
+/// Payload longer than the slot capacity; nothing was written.
+pub const BUS_ERR_TOO_LARGE: i32 = -3;
+/// `seq` 0 is reserved as the empty sentinel.
+pub const BUS_ERR_SEQ: i32 = -4;
+
+/// Open (or create) the bus `name`. Returns null on failure.
+/// The handle must be released with `bus_free`.
+///
+/// # Safety
+/// `name` must be null or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bus_open_or_create(name: *const c_char) -> *mut Bus {
+    if name.is_null() {
+        return core::ptr::null_mut();
+    }
+    let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+        return core::ptr::null_mut();
+    };
+    match catch_unwind(|| Bus::try_open_or_create(name)) {
+        Ok(Ok(bus)) => Box::into_raw(Box::new(bus)),
+        Ok(Err(_)) | Err(_) => core::ptr::null_mut(),
+    }
+}
+
+/// Store `len` bytes at `data` as message `seq` on `topic`. Does not notify.
+///
+/// # Safety
+/// `bus` must come from `bus_open_or_create`; `data` must point to `len` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bus_write(bus: *mut Bus, topic: usize, seq: u64, data: *const u8, len: usize) -> i32 {
+    let Some(bus) = (unsafe { bus.as_ref() }) else {
+        return BUS_ERR_NULL;
+    };
+    if topic >= bus.num_topics() {
+        return BUS_ERR_TOPIC;
+    }
+    if seq == EMPTY_SEQ {
+        return BUS_ERR_SEQ;
+    }
+    if len > bus.capacity(topic) {
+        return BUS_ERR_TOO_LARGE;
+    }
+    let payload = match (data.is_null(), len) {
+        (_, 0) => &[][..],
+        (true, _) => return BUS_ERR_NULL,
+        (false, _) => unsafe { core::slice::from_raw_parts(data, len) },
+    };
+    match catch_unwind(AssertUnwindSafe(|| bus.write(topic, seq, payload))) {
+        Ok(Ok(())) => BUS_OK,
+        Ok(Err(BusError::ReservedSeq)) => BUS_ERR_SEQ,
+        // Otherwise write only fails with PayloadTooLarge; a panic is reported the same way.
+        Ok(Err(_)) | Err(_) => BUS_ERR_TOO_LARGE,
+    }
+}
+
+/// Mark `topic` pending and wake consumers.
+///
+/// # Safety
+/// `bus` must come from `bus_open_or_create`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bus_notify(bus: *mut Bus, topic: usize) -> i32 {
+    let Some(bus) = (unsafe { bus.as_ref() }) else {
+        return BUS_ERR_NULL;
+    };
+    if topic >= bus.num_topics() {
+        return BUS_ERR_TOPIC;
+    }
+    bus.notify(topic);
+    BUS_OK
+}
+
+/// Release a handle from `bus_open_or_create`. Null is a no-op.
+///
+/// # Safety
+/// `bus` must be null or a live handle that is not used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bus_free(bus: *mut Bus) {
+    if !bus.is_null() {
+        drop(unsafe { Box::from_raw(bus) });
+    }
+}
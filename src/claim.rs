@@ -0,0 +1,89 @@
+//! Single-writer claims on topics.
+//!
+//! The seqlock assumes each topic has one writer; two producers on a topic would
+//! interleave their stores and reuse each other's seqs. `claim_producer` records
+//! the claimant's pid per topic in the journal and turns a second claimant away,
+//! unless the recorded process no longer exists (it crashed without releasing).
+
+use core::sync::atomic::Ordering;
+
+use crate::owned::process_exists;
+use crate::{as_atomic_u32, BusError, SizedBus, BITWORDS, MAX_DATA_SIZE, MAX_SLOTS_PER_TOPIC, NUM_TOPICS};
+
+/// Proof that this process is `topic_id`'s only producer; released on drop.
+pub struct ProducerToken<
+    'a,
+    const TOPICS: usize = NUM_TOPICS,
+    const SLOTS: usize = MAX_SLOTS_PER_TOPIC,
+    const DATA: usize = MAX_DATA_SIZE,
+    const WORDS: usize = BITWORDS,
+> {
+    bus: &'a SizedBus<TOPICS, SLOTS, DATA, WORDS>,
+    topic_id: usize,
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    ProducerToken<'_, TOPICS, SLOTS, DATA, WORDS>
+{
+    pub fn topic_id(&self) -> usize {
+        self.topic_id
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize> Drop
+    for ProducerToken<'_, TOPICS, SLOTS, DATA, WORDS>
+{
+    fn drop(&mut self) {
+        self.bus.release_producer(self.topic_id);
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Become `topic_id`'s single writer. Fails with `TopicAlreadyHasProducer` while
+    /// another live process (or another handle in this one) holds the claim; a claim
+    /// left by a dead process is taken over. Only cooperating producers are held
+    /// back: plain `write` doesn't check.
+    pub fn claim_producer(&self, topic_id: usize) -> Result<ProducerToken<'_, TOPICS, SLOTS, DATA, WORDS>, BusError> {
+        self.try_claim_producer(topic_id)?;
+        Ok(ProducerToken { bus: self, topic_id })
+    }
+
+    /// Pid holding `topic_id`'s producer claim, if any.
+    pub fn topic_producer(&self, topic_id: usize) -> Option<u32> {
+        match self.producer_slot(topic_id).load(Ordering::Acquire) {
+            0 => None,
+            pid => Some(pid),
+        }
+    }
+
+    #[inline]
+    fn producer_slot(&self, topic_id: usize) -> &core::sync::atomic::AtomicU32 {
+        unsafe { as_atomic_u32(&(*self.journal).producer_pid[topic_id]) }
+    }
+
+    pub(crate) fn try_claim_producer(&self, topic_id: usize) -> Result<(), BusError> {
+        let me = std::process::id();
+        let slot = self.producer_slot(topic_id);
+        let mut current = 0;
+        loop {
+            match slot.compare_exchange(current, me, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return Ok(()),
+                Err(0) => current = 0,
+                Err(pid) if pid != me && !process_exists(pid) => current = pid,
+                Err(pid) => return Err(BusError::TopicAlreadyHasProducer { topic_id, pid }),
+            }
+        }
+    }
+
+    /// Drop the claim if this process holds it.
+    pub(crate) fn release_producer(&self, topic_id: usize) {
+        let _ = self.producer_slot(topic_id).compare_exchange(
+            std::process::id(),
+            0,
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        );
+    }
+}
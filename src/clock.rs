@@ -0,0 +1,87 @@
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+/// Nanoseconds on the system-wide monotonic clock (CLOCK_MONOTONIC_RAW where
+/// available, QueryPerformanceCounter on Windows), so stamps taken in different
+/// processes on one host are comparable.
+#[cfg(unix)]
+#[inline]
+pub fn monotonic_nanos() -> u64 {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios"))]
+    let clock = libc::CLOCK_MONOTONIC_RAW;
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios")))]
+    let clock = libc::CLOCK_MONOTONIC;
+    unsafe {
+        libc::clock_gettime(clock, &mut ts);
+    }
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32).as_nanos() as u64
+}
+
+#[cfg(windows)]
+#[inline]
+pub fn monotonic_nanos() -> u64 {
+    use windows_sys::Win32::System::Performance::{QueryPerformanceCounter, QueryPerformanceFrequency};
+
+    let (mut ticks, mut freq) = (0i64, 0i64);
+    unsafe {
+        QueryPerformanceCounter(&mut ticks);
+        QueryPerformanceFrequency(&mut freq);
+    }
+    (ticks as u128 * Duration::from_secs(1).as_nanos() / freq.max(1) as u128) as u64
+}
+
+/// Time source for publish timestamps, heartbeats and pacing. Swap in a `MockClock`
+/// (via `BusConfig::clock` or `Producer::with_clock`) to make them deterministic.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Current time in nanoseconds. Only differences matter, but every process on a
+    /// bus must use the same epoch for timestamps to be comparable.
+    fn now_nanos(&self) -> u64;
+
+    /// Let `dur` pass. The system clock sleeps; a mock just moves its time forward.
+    fn sleep(&self, dur: Duration) {
+        std::thread::sleep(dur);
+    }
+}
+
+/// The default clock: `monotonic_nanos()`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    #[inline]
+    fn now_nanos(&self) -> u64 {
+        monotonic_nanos()
+    }
+}
+
+/// Clock that only moves when told to (or when something `sleep`s on it).
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now: AtomicU64,
+}
+
+impl MockClock {
+    pub fn new(start_nanos: u64) -> Self {
+        Self { now: AtomicU64::new(start_nanos) }
+    }
+
+    pub fn set(&self, nanos: u64) {
+        self.now.store(nanos, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, dur: Duration) {
+        self.now.fetch_add(dur.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_nanos(&self) -> u64 {
+        self.now.load(Ordering::Relaxed)
+    }
+
+    fn sleep(&self, dur: Duration) {
+        self.advance(dur);
+    }
+}
@@ -0,0 +1,59 @@
+//! LZ4-compressed payloads (feature `lz4`).
+//!
+//! `write_compressed` stores an LZ4 block, prefixed with the uncompressed length,
+//! and sets `Message::compressed`; payloads that don't shrink are stored raw. The
+//! flag is per message, so a topic can mix both and readers just call
+//! `decompressed`.
+
+use std::borrow::Cow;
+
+use crate::{BusError, Message, SizedBus};
+
+/// LZ4 can't expand more than ~255x, so a length prefix claiming more is corrupt
+/// (and mustn't size an allocation).
+const MAX_RATIO: usize = 255;
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Like `write`, but LZ4-compresses `data` first, so e.g. a 3KB order-book
+    /// snapshot can fit a 1KB slot. Falls back to storing `data` raw when
+    /// compressing doesn't make it smaller. `PayloadTooLarge` reports the stored
+    /// size if even the smaller form doesn't fit `capacity(topic_id)`.
+    pub fn write_compressed(&self, topic_id: usize, seq: u64, data: &[u8]) -> Result<(), BusError> {
+        let packed = lz4_flex::block::compress_prepend_size(data);
+        if packed.len() >= data.len() {
+            return self.write(topic_id, seq, data);
+        }
+        let max = self.capacity(topic_id);
+        if packed.len() > max {
+            return Err(BusError::PayloadTooLarge { len: packed.len(), max });
+        }
+        self.write_message(topic_id, Message { compressed: true, ..Message::with_payload(seq, &packed, self.now()) })
+    }
+
+    /// `read` followed by `Message::decompressed`: the original bytes of `seq`, or
+    /// None if the slot doesn't hold it.
+    pub fn read_decompressed(&self, topic_id: usize, seq: u64) -> Option<Result<Vec<u8>, BusError>> {
+        self.read(topic_id, seq).map(|msg| msg.decompressed().map(Cow::into_owned))
+    }
+}
+
+impl Message {
+    /// The payload as written: inflated if `compressed`, borrowed as is otherwise.
+    /// `BusError::Decompress` if the stored block is corrupt.
+    pub fn decompressed(&self) -> Result<Cow<'_, [u8]>, BusError> {
+        if !self.compressed {
+            return Ok(Cow::Borrowed(self.payload()));
+        }
+        let payload = self.payload();
+        let claimed = payload.get(..4).map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize);
+        match claimed {
+            Some(len) if len <= payload.len().saturating_mul(MAX_RATIO) => {}
+            _ => return Err(BusError::Decompress("bad uncompressed length prefix".into())),
+        }
+        lz4_flex::block::decompress_size_prepended(payload)
+            .map(Cow::Owned)
+            .map_err(|e| BusError::Decompress(e.to_string()))
+    }
+}
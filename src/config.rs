@@ -0,0 +1,230 @@
+use core::time::Duration;
+use std::sync::Arc;
+
+use crate::{
+    Backing, Clock, MonotonicClock, NotifyBackend, OrderingProfile, SubscriptionMask, DATA_SIZE, MAX_DATA_SIZE,
+    MAX_PAYLOAD_ALIGN, MAX_SLOTS_PER_TOPIC, NUM_CHANNELS, NUM_TOPICS, SLOTS_PER_TOPIC,
+};
+
+/// Options applied when a bus segment is first created. A process attaching to an
+/// existing segment takes the layout recorded in its `JournalHeader` instead, so
+/// producers and consumers always agree even if their configs differ.
+///
+/// The naming options are the exception: they decide which semaphore and segment a
+/// process opens, so every process on a bus must pass the same `sem_prefix`, `backing`
+/// and `notify_backend`.
+#[derive(Clone, Debug)]
+pub struct BusConfig {
+    pub(crate) pad_pending: bool,
+    pub(crate) num_topics: usize,
+    pub(crate) channel_data_size: [u32; NUM_CHANNELS],
+    pub(crate) channel_slots: [u32; NUM_CHANNELS],
+    pub(crate) payload_align: u32,
+    pub(crate) sem_prefix: String,
+    pub(crate) sem_mode: u32,
+    pub(crate) backing: Backing,
+    pub(crate) coalesce: SubscriptionMask,
+    pub(crate) notify_backend: NotifyBackend,
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) orderings: OrderingProfile,
+    pub(crate) fixed_addr: Option<usize>,
+    pub(crate) post_window: Duration,
+    #[cfg(feature = "crypto")]
+    pub(crate) payload_key: Option<crate::PayloadKey>,
+}
+
+/// Namespace the notify semaphore / event name starts with unless configured.
+#[cfg(unix)]
+const DEFAULT_SEM_PREFIX: &str = "/";
+#[cfg(windows)]
+const DEFAULT_SEM_PREFIX: &str = "Local\\";
+
+impl Default for BusConfig {
+    fn default() -> Self {
+        Self {
+            pad_pending: false,
+            num_topics: NUM_TOPICS,
+            channel_data_size: [DATA_SIZE as u32; NUM_CHANNELS],
+            channel_slots: [SLOTS_PER_TOPIC as u32; NUM_CHANNELS],
+            payload_align: 8,
+            sem_prefix: DEFAULT_SEM_PREFIX.to_owned(),
+            sem_mode: 0o666,
+            backing: Backing::Shm,
+            coalesce: SubscriptionMask::new(),
+            notify_backend: NotifyBackend::Semaphore,
+            clock: Arc::new(MonotonicClock),
+            orderings: OrderingProfile::AcquireRelease,
+            fixed_addr: None,
+            post_window: Duration::ZERO,
+            #[cfg(feature = "crypto")]
+            payload_key: None,
+        }
+    }
+}
+
+impl BusConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give each pending-bitmap word its own 64-byte cache line, so producers
+    /// notifying topics in different words don't bounce the same line between cores.
+    pub fn pad_pending(mut self, on: bool) -> Self {
+        self.pad_pending = on;
+        self
+    }
+
+    /// Topics the segment holds (default and most `NUM_TOPICS`): only ids `0..n` get a
+    /// ring and a pending bit, so a bus for a few instruments maps a small arena and
+    /// its drains walk `n.div_ceil(64)` words.
+    ///
+    /// Panics if `n` is 0 or over `NUM_TOPICS`.
+    pub fn num_topics(mut self, n: usize) -> Self {
+        assert!(n > 0 && n <= NUM_TOPICS, "num_topics must be in 1..={}", NUM_TOPICS);
+        self.num_topics = n;
+        self
+    }
+
+    /// Payload capacity of every slot on `channel` (default `DATA_SIZE`), so small
+    /// trade prints and large book snapshots don't reserve the same stride.
+    ///
+    /// Panics if `bytes` is 0 or over `MAX_DATA_SIZE`, or `channel` is out of range.
+    pub fn channel_data_size(mut self, channel: usize, bytes: usize) -> Self {
+        assert!(channel < NUM_CHANNELS, "channel {} out of range", channel);
+        assert!(bytes > 0 && bytes <= MAX_DATA_SIZE, "channel data size must be in 1..={}", MAX_DATA_SIZE);
+        self.channel_data_size[channel] = bytes as u32;
+        self
+    }
+
+    /// Ring depth of every topic on `channel` (default SLOTS_PER_TOPIC), so book
+    /// channels can keep a long history while trade channels keep a short one, and
+    /// the segment only pays for the depth each channel needs. `coalesce` still
+    /// makes an individual topic a single slot.
+    ///
+    /// Panics if `slots` is 0 or over `MAX_SLOTS_PER_TOPIC`, or `channel` is out of range.
+    pub fn channel_slots(mut self, channel: usize, slots: usize) -> Self {
+        assert!(channel < NUM_CHANNELS, "channel {} out of range", channel);
+        assert!(slots > 0 && slots <= MAX_SLOTS_PER_TOPIC, "channel depth must be in 1..={}", MAX_SLOTS_PER_TOPIC);
+        self.channel_slots[channel] = slots as u32;
+        self
+    }
+
+    /// Start every slot's payload in the segment on a `bytes` boundary (default 8),
+    /// e.g. 32 or 64 so a consumer can run aligned SIMD loads straight over
+    /// `MessageRef::data`. Each slot's stride becomes the 40-byte slot header plus the
+    /// channel's capacity, rounded up to `bytes` (see `Bus::stride`), so large
+    /// alignments cost padding on small channels.
+    ///
+    /// Panics unless `bytes` is a power of two in `8..=MAX_PAYLOAD_ALIGN`.
+    pub fn payload_align(mut self, bytes: usize) -> Self {
+        assert!(
+            bytes.is_power_of_two() && (8..=MAX_PAYLOAD_ALIGN).contains(&bytes),
+            "payload alignment must be a power of two in 8..={}",
+            MAX_PAYLOAD_ALIGN
+        );
+        self.payload_align = bytes as u32;
+        self
+    }
+
+    /// Name the notify semaphore `{prefix}{name}-notify` (default prefix `/`, or
+    /// `Local\` for the Windows event), so same-named buses of different tenants
+    /// stay apart. On unix the prefix must start with `/` and contain no other `/`.
+    pub fn sem_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.sem_prefix = prefix.into();
+        self
+    }
+
+    /// Permission bits for the semaphore when this process creates it (default
+    /// `0o666`, further masked by the umask). Ignored on Windows.
+    pub fn sem_mode(mut self, mode: u32) -> Self {
+        self.sem_mode = mode;
+        self
+    }
+
+    /// Make `topic_id` latest-only: its ring is a single slot that every `write`
+    /// overwrites in place, and `read_latest` is one seqlock read. For topics like
+    /// quotes where only the freshest value matters and catch-up is unwanted.
+    pub fn coalesce(mut self, topic_id: usize) -> Self {
+        self.coalesce.set(topic_id);
+        self
+    }
+
+    /// `coalesce` every topic in `topics`.
+    pub fn coalesce_topics(mut self, topics: &SubscriptionMask) -> Self {
+        self.coalesce = self.coalesce.union(topics);
+        self
+    }
+
+    /// Where the segment lives (default `Backing::Shm`). The notify semaphore stays a
+    /// named semaphore for `Shm` and `File`; `Heap` brings its own.
+    pub fn backing(mut self, backing: Backing) -> Self {
+        self.backing = backing;
+        self
+    }
+
+    /// Map the segment read-write at exactly `addr` (page-aligned), and have `reopen`
+    /// put the new segment at the same address, so raw pointers into the slots
+    /// survive a producer restart. The open fails with `BusError::Open` if anything
+    /// is already mapped there (never clobbered: `MAP_FIXED_NOREPLACE` on Linux),
+    /// including another handle in this process on an older segment of the same
+    /// name, and `reopen` fails, leaving the handle as it was, if the new segment is
+    /// bigger than the old. Unix only, `Shm` and `File` backings only; read-only
+    /// opens ignore it. `None` (the default) lets the kernel choose.
+    pub fn fixed_addr(mut self, addr: Option<usize>) -> Self {
+        self.fixed_addr = addr;
+        self
+    }
+
+    /// How consumers are woken (default `NotifyBackend::Semaphore`). Pick
+    /// `NotifyBackend::Fifo` to wait on the bus from an epoll loop via `Bus::notify_fd`.
+    pub fn notify_backend(mut self, backend: NotifyBackend) -> Self {
+        self.notify_backend = backend;
+        self
+    }
+
+    /// Post each group's semaphore at most once per `window` from this handle (default
+    /// zero: on every 0 -> 1 pending transition), so a bursty producer wakes a slow
+    /// consumer once per window rather than after every drain. A post inside the
+    /// window is held until the next `notify` after it ends, `Bus::flush_posts`, or
+    /// the handle's drop. The bits are set immediately, and the window is recorded
+    /// in the segment header, so consumers' waits wake at least once per (longest)
+    /// window to pick them up even if the producer goes quiet.
+    pub fn post_window(mut self, window: Duration) -> Self {
+        self.post_window = window;
+        self
+    }
+
+    /// Encrypt payloads in the segment under `key` (feature `crypto`; see
+    /// `PayloadKey`). Like the naming options this isn't recorded in the header:
+    /// every process on the bus must pass the same key, or it reads the others'
+    /// payloads as noise.
+    #[cfg(feature = "crypto")]
+    pub fn payload_key(mut self, key: crate::PayloadKey) -> Self {
+        self.payload_key = Some(key);
+        self
+    }
+
+    /// Where this process's handle takes publish timestamps and heartbeats from
+    /// (default `MonotonicClock`). Per process, but stamps are compared across
+    /// processes, so they should agree on the epoch.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Memory orderings for this handle's `notify`, drains and seqlock (default
+    /// `OrderingProfile::AcquireRelease`), for benchmarking on specific hardware.
+    /// Each process picks its own; mixing profiles on one bus gives the weakest
+    /// one's guarantees.
+    ///
+    /// # Safety
+    ///
+    /// With anything weaker than the default, a consumer can observe a notify
+    /// before the message it announces, or accept a torn read as consistent. The
+    /// caller takes responsibility that this can't happen on its target, compiler
+    /// and workload, or that it doesn't matter (e.g. a throughput-only benchmark).
+    pub unsafe fn orderings(mut self, profile: OrderingProfile) -> Self {
+        self.orderings = profile;
+        self
+    }
+}
@@ -0,0 +1,82 @@
+//! Payload encryption at rest (feature `crypto`).
+//!
+//! With `BusConfig::payload_key` set, a handle XORs every payload with a ChaCha20
+//! keystream on its way into the slot and again on its way out, so what sits in the
+//! segment (`/dev/shm`, a core dump) is ciphertext. The nonce is `(topic_id, seq)`,
+//! so nothing extra is stored; seq, length, type, timestamps and the pending bits
+//! stay cleartext. The stored CRC covers the ciphertext and is checked before
+//! decrypting, so `Message::crc_ok` still reports corruption after a `read`.
+//!
+//! This guards payloads against casual snooping, nothing more. Nothing is
+//! authenticated, and writing one `(topic_id, seq)` twice with different data (a
+//! rewound producer, a segment recreated under the same key) reuses its keystream.
+//! `read_ref` borrows the stored bytes, so it sees ciphertext, and a `begin_write`
+//! payload is plaintext in its slot until `commit`.
+
+use core::fmt;
+
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+
+use crate::{crc32, Message, SizedBus};
+
+/// 256-bit ChaCha20 key for `BusConfig::payload_key`. `Debug` doesn't print it.
+#[derive(Clone)]
+pub struct PayloadKey([u8; 32]);
+
+impl PayloadKey {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// XOR `data` with the keystream for `(topic_id, seq)`; doing it twice restores it.
+    fn apply(&self, topic_id: usize, seq: u64, data: &mut [u8]) {
+        let mut nonce = [0u8; 12];
+        nonce[..4].copy_from_slice(&(topic_id as u32).to_le_bytes());
+        nonce[4..].copy_from_slice(&seq.to_le_bytes());
+        ChaCha20::new(&self.0.into(), &nonce.into()).apply_keystream(data);
+    }
+}
+
+impl fmt::Debug for PayloadKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PayloadKey(..)")
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Encrypt the payload of `(topic_id, seq)` in place, if this handle has a key.
+    #[inline]
+    pub(crate) fn seal_payload(&self, topic_id: usize, seq: u64, payload: &mut [u8]) {
+        if let Some(key) = &self.config.payload_key {
+            key.apply(topic_id, seq, payload);
+        }
+    }
+
+    /// `msg` as it is stored: payload encrypted, CRC over the ciphertext.
+    #[inline]
+    pub(crate) fn sealed(&self, topic_id: usize, mut msg: Message) -> Message {
+        if self.config.payload_key.is_some() {
+            let len = msg.payload().len();
+            self.seal_payload(topic_id, msg.seq, &mut msg.data[..len]);
+            msg.crc = crc32(&msg.data[..len]);
+        }
+        msg
+    }
+
+    /// Undo `sealed` on a message just read. The CRC is carried over to the plaintext
+    /// only if the ciphertext matched it, so a corrupt payload still fails `crc_ok`.
+    #[inline]
+    pub(crate) fn open_in_place(&self, topic_id: usize, msg: &mut Message) {
+        if let Some(key) = &self.config.payload_key {
+            let len = msg.payload().len();
+            let intact = crc32(&msg.data[..len]) == msg.crc;
+            key.apply(topic_id, msg.seq, &mut msg.data[..len]);
+            if intact {
+                msg.crc = crc32(&msg.data[..len]);
+            }
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Owned producer and consumer handles wrapping the usage patterns of the demo binary.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::{
+    next_seq, Bus, BusError, Clock, Message, RateLimiter, SizedBus, SubscriptionMask, BITWORDS, EMPTY_SEQ,
+    MAX_DATA_SIZE, MAX_SLOTS_PER_TOPIC, NUM_TOPICS,
+};
+
+/// How often a `Subscription`'s thread checks whether it should stop.
+const STOP_POLL: Duration = Duration::from_millis(50);
+
+/// Publishes to one topic, numbering messages itself.
+pub struct Producer<
+    const TOPICS: usize = NUM_TOPICS,
+    const SLOTS: usize = MAX_SLOTS_PER_TOPIC,
+    const DATA: usize = MAX_DATA_SIZE,
+    const WORDS: usize = BITWORDS,
+> {
+    bus: SizedBus<TOPICS, SLOTS, DATA, WORDS>,
+    topic_id: usize,
+    seq: u64,
+    rate: Option<RateLimiter>,
+    clock: Arc<dyn Clock>,
+    /// Holds the topic's `claim_producer` claim, released on drop.
+    exclusive: bool,
+}
+
+impl Producer {
+    /// On the default-sized bus `name`; other sizes go through `new`.
+    pub fn open(name: &str, topic_id: usize) -> Self {
+        Self::new(Bus::open_or_create(name), topic_id)
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    Producer<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Take over `topic_id`, continuing after its latest resident seq so a restarted
+    /// producer never rewinds the topic.
+    pub fn new(bus: SizedBus<TOPICS, SLOTS, DATA, WORDS>, topic_id: usize) -> Self {
+        assert!(topic_id < bus.num_topics(), "topic_id {} out of range", topic_id);
+        let seq = next_seq(bus.get_latest_seq(topic_id));
+        let clock = bus.clock().clone();
+        Self { bus, topic_id, seq, rate: None, clock, exclusive: false }
+    }
+
+    /// Like `new`, but first claims the topic (`Bus::claim_producer`), so a second
+    /// producer started on it fails here instead of clobbering this one's seqs.
+    /// The seq is read after the claim, so it continues from a dead predecessor.
+    pub fn exclusive(bus: SizedBus<TOPICS, SLOTS, DATA, WORDS>, topic_id: usize) -> Result<Self, BusError> {
+        assert!(topic_id < bus.num_topics(), "topic_id {} out of range", topic_id);
+        bus.try_claim_producer(topic_id)?;
+        let mut producer = Self::new(bus, topic_id);
+        producer.exclusive = true;
+        Ok(producer)
+    }
+
+    /// Pace `publish` to at most `hz` messages per second (see `RateLimiter`), so a
+    /// tight publish loop can't saturate the ring. Panics if `hz` is 0.
+    pub fn with_rate(mut self, hz: u64) -> Self {
+        self.rate = Some(RateLimiter::with_clock(hz, self.clock.clone()));
+        self
+    }
+
+    /// Stamp messages and pace on `clock` instead of the bus's `BusConfig::clock`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        if let Some(rate) = &mut self.rate {
+            rate.set_clock(clock.clone());
+        }
+        self.clock = clock;
+        self
+    }
+
+    /// Write `data` as the next message and notify, first waiting out the rate limit
+    /// if one is set. Returns the seq it was given; on error nothing was written and
+    /// the seq is not used up.
+    pub fn publish(&mut self, data: &[u8]) -> Result<u64, BusError> {
+        self.publish_at(self.seq, data)
+    }
+
+    /// `publish` for a caller that numbers messages itself (e.g. after an upstream
+    /// feed): fails with `BusError::NonContiguousSeq` unless `seq` is `self.seq()`,
+    /// so a numbering bug shows up here rather than as a gap on every consumer,
+    /// where it would pass for messages the ring overwrote.
+    pub fn publish_seq(&mut self, seq: u64, data: &[u8]) -> Result<u64, BusError> {
+        if seq != self.seq {
+            return Err(BusError::NonContiguousSeq { topic_id: self.topic_id, expected: self.seq, got: seq });
+        }
+        self.publish_at(seq, data)
+    }
+
+    /// `publish` with `seq` taken as given, for a deliberate jump (consumers see the
+    /// skipped seqs as a gap). Numbering continues after `seq`.
+    pub fn publish_at(&mut self, seq: u64, data: &[u8]) -> Result<u64, BusError> {
+        if let Some(rate) = &mut self.rate {
+            rate.pace();
+        }
+        self.bus.write_at(self.topic_id, seq, data, self.clock.now_nanos())?;
+        self.bus.notify(self.topic_id);
+        self.seq = next_seq(seq);
+        Ok(seq)
+    }
+
+    /// Reserve the next `n` seqs for messages written outside `publish`, e.g. in one
+    /// `Bus::write_batch` with a single notify. Numbering continues after the block,
+    /// so the topic stays contiguous as long as every claimed seq gets written.
+    /// Panics if the block would run past `u64::MAX`.
+    pub fn claim_seqs(&mut self, n: u64) -> Range<u64> {
+        let end = self.seq.checked_add(n).expect("claim_seqs would wrap the seq space");
+        let claimed = self.seq..end;
+        self.seq = end;
+        claimed
+    }
+
+    /// The limiter set by `with_rate`, with its throttling count.
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate.as_ref()
+    }
+
+    /// The seq the next `publish` will use.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn topic_id(&self) -> usize {
+        self.topic_id
+    }
+
+    pub fn bus(&self) -> &SizedBus<TOPICS, SLOTS, DATA, WORDS> {
+        &self.bus
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize> Drop
+    for Producer<TOPICS, SLOTS, DATA, WORDS>
+{
+    fn drop(&mut self) {
+        if self.exclusive {
+            self.bus.release_producer(self.topic_id);
+        }
+    }
+}
+
+/// Waits on a bus and hands over every message on the subscribed topics that it
+/// hasn't delivered yet, oldest first.
+///
+/// Per topic, delivery is at most once and in increasing seq order (by `seq_newer`):
+/// a repeated notify with no new write delivers nothing, and a message older than
+/// one already delivered is never handed over. It is not at-least-once: messages
+/// the ring overwrote before a drain reached them are skipped, leaving a gap.
+pub struct Consumer<
+    const TOPICS: usize = NUM_TOPICS,
+    const SLOTS: usize = MAX_SLOTS_PER_TOPIC,
+    const DATA: usize = MAX_DATA_SIZE,
+    const WORDS: usize = BITWORDS,
+> {
+    bus: SizedBus<TOPICS, SLOTS, DATA, WORDS>,
+    mask: SubscriptionMask,
+    last_seen: Vec<u64>,
+    started: bool,
+}
+
+impl Consumer {
+    /// On the default-sized bus `name`; other sizes go through `new`.
+    pub fn open(name: &str, mask: SubscriptionMask) -> Self {
+        Self::new(Bus::open_or_create(name), mask)
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    Consumer<TOPICS, SLOTS, DATA, WORDS>
+{
+    pub fn new(bus: SizedBus<TOPICS, SLOTS, DATA, WORDS>, mask: SubscriptionMask) -> Self {
+        let last_seen = vec![EMPTY_SEQ; bus.num_topics()];
+        Self { bus, mask, last_seen, started: false }
+    }
+
+    /// Wait for notifications and call `on_msg(topic_id, msg)` for each new message
+    /// on a pending subscribed topic. Messages the ring overwrote before they could be
+    /// read are skipped. The first call drains what is already pending without waiting.
+    ///
+    /// Returns false once the bus is shutting down, so `while consumer.poll(..) {}`
+    /// is a complete consumer loop.
+    pub fn poll<F: FnMut(usize, &Message)>(&mut self, mut on_msg: F) -> bool {
+        let (bus, last_seen) = (&self.bus, &mut self.last_seen);
+        let mut on_topic = |t: usize| deliver_new(bus, t, &mut last_seen[t], &mut on_msg);
+        if self.started {
+            bus.wait_and_drain_mask(&self.mask, &mut on_topic);
+        } else {
+            bus.drain_now(&self.mask, &mut on_topic);
+            self.started = true;
+        }
+        !bus.is_shutting_down()
+    }
+
+    /// `poll` that stops waiting after `timeout`, delivering nothing, so the loop can
+    /// do work of its own in between.
+    pub fn poll_timeout<F: FnMut(usize, &Message)>(&mut self, timeout: Duration, mut on_msg: F) -> bool {
+        let (bus, last_seen) = (&self.bus, &mut self.last_seen);
+        let mut on_topic = |t: usize| deliver_new(bus, t, &mut last_seen[t], &mut on_msg);
+        if self.started {
+            bus.wait_and_drain_mask_timeout(&self.mask, timeout, &mut on_topic);
+        } else {
+            bus.drain_now(&self.mask, &mut on_topic);
+            self.started = true;
+        }
+        !bus.is_shutting_down()
+    }
+
+    /// Highest seq delivered on `topic_id`, or `EMPTY_SEQ` if none yet.
+    pub fn last_seen(&self, topic_id: usize) -> u64 {
+        self.last_seen[topic_id]
+    }
+
+    pub fn mask(&self) -> &SubscriptionMask {
+        &self.mask
+    }
+
+    /// Subscribe to `mask` instead, from the next `poll` on. Topics dropped from the
+    /// subscription have their pending bits discarded unprocessed, so a stale wakeup
+    /// for them delivers nothing. Newly added topics start after their current latest
+    /// seq: only messages written from now on are delivered (read the backlog with
+    /// `bus().read_latest` if a snapshot is wanted).
+    pub fn update_mask(&mut self, mask: SubscriptionMask) {
+        self.bus.discard_pending(&self.mask.difference(&mask));
+        for t in mask.difference(&self.mask) {
+            self.last_seen[t] = self.bus.get_latest_seq(t);
+        }
+        self.mask = mask;
+    }
+
+    pub fn bus(&self) -> &SizedBus<TOPICS, SLOTS, DATA, WORDS> {
+        &self.bus
+    }
+}
+
+/// A `Consumer` running on a thread of its own, started by `Bus::subscribe`.
+/// Dropping it stops the thread (within `STOP_POLL`) and joins it, so no thread is
+/// left parked on the semaphore.
+pub struct Subscription {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Consume `mask` on a new thread: `on_msg(topic_id, msg)` is called for every new
+    /// message, with `Consumer::poll`'s delivery guarantees, until the returned
+    /// `Subscription` is dropped or the bus shuts down.
+    pub fn subscribe<F>(self, mask: SubscriptionMask, mut on_msg: F) -> Subscription
+    where
+        F: FnMut(usize, &Message) + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            let mut consumer = Consumer::new(self, mask);
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Acquire) && consumer.poll_timeout(STOP_POLL, &mut on_msg) {}
+            })
+        };
+        Subscription { stop, thread: Some(thread) }
+    }
+}
+
+impl Subscription {
+    /// Stop the thread and wait for it; the same as dropping the handle.
+    pub fn unsubscribe(self) {}
+
+    /// Whether the thread has ended (the bus shut down, or `on_msg` panicked).
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(|t| t.is_finished())
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            // A panic in `on_msg` already ended the thread; don't raise it again here.
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Deliver the resident messages of `topic_id` newer than `last_seen`, in seq order.
+/// One pass over the ring copes with any seq pattern (gaps, skipped seqs), where
+/// stepping `last_seen + 1, + 2, ...` would stall at the first seq never written.
+fn deliver_new<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize, F>(
+    bus: &SizedBus<TOPICS, SLOTS, DATA, WORDS>,
+    topic_id: usize,
+    last_seen: &mut u64,
+    on_msg: &mut F,
+) where
+    F: FnMut(usize, &Message),
+{
+    for msg in bus.topic(topic_id).read_since(*last_seen) {
+        on_msg(topic_id, &msg);
+        *last_seen = msg.seq;
+    }
+}
@@ -0,0 +1,132 @@
+use core::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BusError {
+    /// Payload doesn't fit in a slot (or, for `write_large`, in a topic's ring);
+    /// nothing was written.
+    PayloadTooLarge { len: usize, max: usize },
+    /// Every consumer ack row is already claimed.
+    TooManyConsumers { max: usize },
+    /// The shared memory segment couldn't be created or opened.
+    Open(String),
+    /// The mapping is smaller than the layout its header describes; using it would
+    /// read and write past the end.
+    SegmentTooSmall { got: usize, need: usize },
+    /// The creator never finished initializing the header.
+    NotInitialized,
+    /// `create_exclusive` found a segment already under the name.
+    AlreadyExists,
+    /// `Bus::open` (or another attach-only open) found no segment under the name,
+    /// e.g. because the producer isn't up yet.
+    NotFound,
+    /// The segment was created by a build with a different layout.
+    IncompatibleLayout { layout_version: u32 },
+    /// The notify semaphore / event couldn't be opened.
+    Notifier(String),
+    /// `try_write` would have reused the slot holding `unacked`, which some registered
+    /// consumer hasn't acked yet; nothing was written.
+    WouldOverwriteUnacked { topic_id: usize, unacked: u64 },
+    /// A compressed payload didn't inflate (corrupt block or length prefix).
+    Decompress(String),
+    /// `register_topic` was given a name longer than `MAX_TOPIC_NAME_LEN` bytes.
+    TopicNameTooLong { len: usize, max: usize },
+    /// Every topic id already has a registered name.
+    RegistryFull { max: usize },
+    /// `register_topic` probed to entry `topic_id`, claimed but never published (its
+    /// registrant likely died mid-store), so it can't tell whether the name is taken.
+    RegistryBusy { topic_id: usize },
+    /// `claim_producer`: live process `pid` already writes `topic_id`.
+    TopicAlreadyHasProducer { topic_id: usize, pid: u32 },
+    /// `Producer::publish_seq` was given `got` where the topic's next seq is
+    /// `expected`; nothing was written.
+    NonContiguousSeq { topic_id: usize, expected: u64, got: u64 },
+    /// A write was given seq `EMPTY_SEQ`, which marks an empty slot; nothing was written.
+    ReservedSeq,
+}
+
+impl fmt::Display for BusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BusError::PayloadTooLarge { len, max } => {
+                write!(f, "payload of {} bytes exceeds the {} byte capacity", len, max)
+            }
+            BusError::TooManyConsumers { max } => {
+                write!(f, "all {} consumer slots are registered", max)
+            }
+            BusError::Open(e) => write!(f, "cannot create/open shared memory: {}", e),
+            BusError::SegmentTooSmall { got, need } => {
+                write!(f, "segment is {} bytes but its layout needs {}", got, need)
+            }
+            BusError::NotInitialized => write!(f, "segment was never initialized by its creator"),
+            BusError::AlreadyExists => write!(f, "a segment with this name already exists"),
+            BusError::NotFound => write!(f, "no segment with this name exists"),
+            BusError::IncompatibleLayout { layout_version } => {
+                write!(f, "incompatible layout (layout_version {})", layout_version)
+            }
+            BusError::Notifier(e) => write!(f, "notifier open failed: {}", e),
+            BusError::WouldOverwriteUnacked { topic_id, unacked } => {
+                write!(f, "topic {} ring is full: seq {} is not acked yet", topic_id, unacked)
+            }
+            BusError::Decompress(e) => write!(f, "cannot decompress payload: {}", e),
+            BusError::TopicNameTooLong { len, max } => {
+                write!(f, "topic name of {} bytes exceeds the {} byte limit", len, max)
+            }
+            BusError::RegistryFull { max } => write!(f, "all {} topic ids have registered names", max),
+            BusError::RegistryBusy { topic_id } => {
+                write!(f, "registry entry {} was claimed but never published", topic_id)
+            }
+            BusError::TopicAlreadyHasProducer { topic_id, pid } => {
+                write!(f, "topic {} already has a producer (pid {})", topic_id, pid)
+            }
+            BusError::NonContiguousSeq { topic_id, expected, got } => {
+                write!(f, "topic {} expects seq {} next, not {}", topic_id, expected, got)
+            }
+            BusError::ReservedSeq => write!(f, "seq 0 is reserved as EMPTY_SEQ"),
+        }
+    }
+}
+
+impl std::error::Error for BusError {}
+
+/// Stored CRC doesn't match the payload read back from a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntegrityError {
+    pub topic_id: usize,
+    pub seq: u64,
+    pub stored: u32,
+    pub computed: u32,
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "crc mismatch on topic {} seq {}: stored {:#010x}, computed {:#010x}",
+            self.topic_id, self.seq, self.stored, self.computed
+        )
+    }
+}
+
+impl std::error::Error for IntegrityError {}
+
+/// A `PayloadWriter` / `PayloadReader` field ran past the end of its buffer; the
+/// cursor didn't move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadOverrun {
+    /// Cursor position when the field was attempted.
+    pub offset: usize,
+    pub need: usize,
+    pub remaining: usize,
+}
+
+impl fmt::Display for PayloadOverrun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-byte field at offset {} overruns the payload ({} bytes left)",
+            self.need, self.offset, self.remaining
+        )
+    }
+}
+
+impl std::error::Error for PayloadOverrun {}
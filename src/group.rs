@@ -0,0 +1,186 @@
+//! Consumer groups: independent pending bitmaps and semaphores on one journal.
+//!
+//! Group 0 is the bus's own bitmap and semaphore, notified for every topic. Groups
+//! `1..NUM_GROUPS` are opened with `Bus::open_or_create_group` and a subscription
+//! mask; `notify` then also marks the topic in, and posts, each active group whose
+//! mask contains it, so e.g. a risk and an execution consumer wake independently.
+//! A group's mask only grows (handles joining with other masks add to it) and a
+//! group stays active for the life of the segment.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::notify::{self, Notifier};
+use crate::{as_atomic, BusConfig, BusError, OpenMode, PendingBits, SizedBus, SubscriptionMask, BITWORDS};
+
+/// Notification groups per bus, including the default group 0.
+pub const NUM_GROUPS: usize = 4;
+
+/// Per-group state in the journal. Index 0 of each array is unused: the default
+/// group lives in `Journal::pending_bits`.
+#[repr(C)]
+pub struct Groups<const WORDS: usize = BITWORDS> {
+    /// Bit `g` is set once group `g` has a subscriber.
+    pub active: u64,
+    pub masks: [[u64; WORDS]; NUM_GROUPS],
+    pub pending: [PendingBits<WORDS>; NUM_GROUPS],
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Attach as a consumer of group `group_id`, adding `mask` to the topics the group
+    /// is notified for. `wait`, `drain_pending_mask` and the other consumer calls on the
+    /// returned handle use the group's bitmap and semaphore. Panics on failure, like
+    /// `open_or_create`, or if `group_id` isn't in `1..NUM_GROUPS`.
+    pub fn open_or_create_group(name: &str, group_id: usize, mask: &SubscriptionMask) -> Self {
+        Self::try_open_or_create_group_with(name, &BusConfig::default(), group_id, mask)
+            .unwrap_or_else(|e| panic!("bus '{}': {}", name, e))
+    }
+
+    /// Fallible `open_or_create_group` with a config. Notifies issued before the mask
+    /// is registered aren't delivered to the group, so drain (or `snapshot`) once after
+    /// joining.
+    pub fn try_open_or_create_group_with(
+        name: &str,
+        config: &BusConfig,
+        group_id: usize,
+        mask: &SubscriptionMask,
+    ) -> Result<Self, BusError> {
+        assert!((1..NUM_GROUPS).contains(&group_id), "group_id {} out of range 1..{}", group_id, NUM_GROUPS);
+        let bus = Self::open_inner(name, config, OpenMode::Create, group_id)?;
+        bus.join_group(group_id, mask.words());
+        Ok(bus)
+    }
+
+    /// Add `mask` to group `g`'s subscription and mark the group active.
+    pub(crate) fn join_group(&self, g: usize, mask: &[u64]) {
+        let groups = self.groups();
+        for (word, &bits) in groups.masks[g].iter().zip(mask) {
+            unsafe { as_atomic(word) }.fetch_or(bits, Ordering::Relaxed);
+        }
+        // Release: a producer that sees the active bit also sees the mask.
+        unsafe { as_atomic(&groups.active) }.fetch_or(1 << g, Ordering::Release);
+    }
+
+    /// This handle's group's current mask (all its members' masks combined).
+    pub(crate) fn group_mask(&self) -> [u64; WORDS] {
+        core::array::from_fn(|w| self.group_mask_word(self.group, w))
+    }
+
+    /// The notification group this handle consumes from (0 unless opened as a group).
+    #[inline]
+    pub fn group(&self) -> usize {
+        self.group
+    }
+
+    #[inline]
+    fn groups(&self) -> &Groups<WORDS> {
+        unsafe { &(*self.journal).groups }
+    }
+
+    /// Word `w` of group `g`'s pending bitmap.
+    #[inline]
+    pub(crate) fn group_pending_word(&self, g: usize, w: usize) -> &AtomicU64 {
+        let bits = match g {
+            0 => unsafe { &(*self.journal).pending_bits },
+            g => &self.groups().pending[g],
+        };
+        unsafe { as_atomic(bits.word(w * self.pending_stride)) }
+    }
+
+    /// Groups other than 0 that have a subscriber, as a bitmask.
+    #[inline]
+    pub(crate) fn active_groups(&self) -> u64 {
+        unsafe { as_atomic(&self.groups().active) }.load(Ordering::Acquire)
+    }
+
+    /// Group `g`'s mask word `w`.
+    #[inline]
+    pub(crate) fn group_mask_word(&self, g: usize, w: usize) -> u64 {
+        unsafe { as_atomic(&self.groups().masks[g][w]) }.load(Ordering::Relaxed)
+    }
+
+    /// Group `g`'s semaphore: this handle's own, or one opened on first use. None if
+    /// it couldn't be opened; the pending bits are still set, so the group's consumer
+    /// finds them on its next wakeup or timeout.
+    pub(crate) fn group_notifier(&self, g: usize) -> Option<&dyn Notifier> {
+        if g == self.group {
+            return Some(&*self.notifier);
+        }
+        self.group_notifiers[g].get_or_init(|| notify::open(&self.name, &self.config, g).ok()).as_deref()
+    }
+
+    /// Mark `bits` of word `w` pending in group `g`; post its semaphore if any bit
+    /// went 0 -> 1. Returns whether it posted.
+    #[inline]
+    pub(crate) fn mark_in_group(&self, g: usize, w: usize, bits: u64) -> bool {
+        let prev = self.group_pending_word(g, w).fetch_or(bits, self.config.orderings.release());
+        let transitioned = bits & !prev != 0;
+        if transitioned {
+            self.post_group(g);
+        }
+        transitioned
+    }
+
+    /// Post group `g`'s semaphore, unless `BusConfig::post_window` holds it back.
+    #[inline]
+    pub(crate) fn post_group(&self, g: usize) {
+        if self.post_window.admit(g, || self.now())
+            && let Some(notifier) = self.group_notifier(g)
+        {
+            notifier.post();
+        }
+    }
+
+    /// Send the posts `post_window` held back whose window is over, or with `force`
+    /// all of them.
+    #[inline]
+    pub(crate) fn post_held(&self, force: bool) {
+        let mut due = self.post_window.take_due(force, || self.now());
+        while due != 0 {
+            let g = due.trailing_zeros() as usize;
+            if let Some(notifier) = self.group_notifier(g) {
+                notifier.post();
+            }
+            due &= due - 1;
+        }
+    }
+
+    /// Producer: send every post `BusConfig::post_window` is holding back now,
+    /// e.g. at the end of a burst, instead of at the next `notify` after the window
+    /// (or when the handle drops). Does nothing without a window.
+    pub fn flush_posts(&self) {
+        self.post_held(true);
+    }
+
+    /// Fan a notify of the `bits` in word `w` out to every active group subscribed to them.
+    #[inline]
+    pub(crate) fn notify_groups(&self, w: usize, bits: u64) {
+        let mut active = self.active_groups();
+        while active != 0 {
+            let g = active.trailing_zeros() as usize;
+            let subscribed = bits & self.group_mask_word(g, w);
+            if subscribed != 0 {
+                self.mark_in_group(g, w, subscribed);
+            }
+            active &= active - 1;
+        }
+    }
+
+    /// Post every active group's semaphore `times` times (used by `shutdown`).
+    pub(crate) fn post_groups(&self, times: u32) {
+        let mut active = self.active_groups();
+        while active != 0 {
+            let g = active.trailing_zeros() as usize;
+            if let Some(notifier) = self.group_notifier(g) {
+                for _ in 0..times {
+                    notifier.post();
+                }
+            }
+            active &= active - 1;
+        }
+    }
+}
+
+// `Groups::active` has one bit per group.
+const _: () = assert!(NUM_GROUPS <= 64);
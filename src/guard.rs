@@ -0,0 +1,121 @@
+//! In-place writes: `Bus::begin_write` hands the producer a slot's data area to
+//! serialize into, instead of a buffer that `write` then copies into the slot. The
+//! guard stores through the slot's atomic words, so readers racing it only ever see
+//! a torn payload that the seqlock then rejects.
+
+use crate::slot::{self, HeaderFields, SlotHeader};
+use crate::{crc32, SizedBus, BITWORDS, EMPTY_SEQ, MAX_DATA_SIZE, MAX_SLOTS_PER_TOPIC, NUM_TOPICS};
+
+/// A slot held mid-write by `Bus::begin_write`. Readers skip it until `commit`
+/// publishes it; dropping the guard uncommitted publishes the slot empty instead.
+pub struct WriteGuard<
+    'a,
+    const TOPICS: usize = NUM_TOPICS,
+    const SLOTS: usize = MAX_SLOTS_PER_TOPIC,
+    const DATA: usize = MAX_DATA_SIZE,
+    const WORDS: usize = BITWORDS,
+> {
+    bus: &'a SizedBus<TOPICS, SLOTS, DATA, WORDS>,
+    topic_id: usize,
+    seq: u64,
+    slot: *mut SlotHeader,
+    /// The odd version `slot::begin` returned.
+    odd: u64,
+    capacity: usize,
+    msg_type: u16,
+    schema_version: u8,
+    committed: bool,
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Start writing message `seq` of `topic_id` straight into its slot: the slot
+    /// goes odd, and `WriteGuard::write` fills its data area (`capacity(topic_id)`
+    /// bytes, holding whatever was there). `commit(len)` publishes the first `len`
+    /// bytes. The old message is gone either way, so a guard dropped without
+    /// committing leaves the slot reading as never written. Like `write`, this
+    /// doesn't notify, and assumes it is the topic's only writer.
+    ///
+    /// Panics if `seq` is `EMPTY_SEQ`, `topic_id` is out of range, or the topic
+    /// already has a guard open on this segment in this process.
+    pub fn begin_write(&self, topic_id: usize, seq: u64) -> WriteGuard<'_, TOPICS, SLOTS, DATA, WORDS> {
+        self.check_topic(topic_id);
+        assert!(seq != EMPTY_SEQ, "seq 0 is reserved as EMPTY_SEQ");
+        assert!(self._mapping.claim_guard(topic_id), "topic_id {} already has a WriteGuard open", topic_id);
+        let topic = &self.layout.topics[topic_id];
+        let slot = self.slot_for(topic, seq);
+        let odd = unsafe { slot::begin(slot, self.config.orderings) };
+        let capacity = topic.capacity;
+        WriteGuard { bus: self, topic_id, seq, slot, odd, capacity, msg_type: 0, schema_version: 0, committed: false }
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    WriteGuard<'_, TOPICS, SLOTS, DATA, WORDS>
+{
+    /// How many bytes the slot's data area holds.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Copy `bytes` into the data area at `offset`. Panics if they run past the
+    /// capacity.
+    pub fn write(&mut self, offset: usize, bytes: &[u8]) {
+        let fits = offset.checked_add(bytes.len()).is_some_and(|end| end <= self.capacity);
+        assert!(fits, "write of {} bytes at {} exceeds the {} byte capacity", bytes.len(), offset, self.capacity);
+        unsafe { slot::store_bytes(self.slot, offset, bytes) };
+    }
+
+    /// Tag the message with `msg_type`, as `write_typed` does.
+    pub fn set_msg_type(&mut self, msg_type: u16) {
+        self.msg_type = msg_type;
+    }
+
+    /// Stamp the payload's `schema_version`, as `write_versioned` does.
+    pub fn set_schema_version(&mut self, schema_version: u8) {
+        self.schema_version = schema_version;
+    }
+
+    /// Publish the first `len` bytes of the data area as the message, stamped now and
+    /// with its CRC (encrypted first under a `payload_key`). Panics if `len` is over
+    /// the capacity.
+    pub fn commit(mut self, len: usize) {
+        assert!(len <= self.capacity, "commit of {} bytes exceeds the {} byte capacity", len, self.capacity);
+        let mut payload = [0u8; MAX_DATA_SIZE];
+        let payload = &mut payload[..len];
+        unsafe { slot::load_payload(self.slot, payload) };
+        self.bus.seal_payload(self.topic_id, self.seq, payload);
+        unsafe { slot::store_payload(self.slot, payload) };
+        let crc = crc32(payload);
+        let fields = HeaderFields {
+            seq: self.seq,
+            publish_ts_nanos: self.bus.now(),
+            data_len: len as u32,
+            crc,
+            msg_type: self.msg_type,
+            schema_version: self.schema_version,
+            flags: 0,
+        };
+        unsafe {
+            slot::store_header(self.slot, &fields);
+            slot::publish(self.slot, self.odd, self.bus.config.orderings);
+        }
+        self.bus.count_write(self.topic_id);
+        self.committed = true;
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize> Drop
+    for WriteGuard<'_, TOPICS, SLOTS, DATA, WORDS>
+{
+    fn drop(&mut self) {
+        if !self.committed {
+            unsafe {
+                slot::store_header(self.slot, &HeaderFields::default());
+                slot::publish(self.slot, self.odd, self.bus.config.orderings);
+            }
+        }
+        self.bus._mapping.release_guard(self.topic_id);
+    }
+}
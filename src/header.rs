@@ -0,0 +1,155 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    as_atomic, as_atomic_u32, BusConfig, BITWORDS, MAX_DATA_SIZE, MAX_PAYLOAD_ALIGN, MAX_SLOTS_PER_TOPIC, NUM_CHANNELS, NUM_TOPICS,
+};
+
+/// "IPCBUS" + layout marker; written last by the creator so attachers can tell
+/// a fully initialized segment from one still being zeroed.
+pub const MAGIC: u64 = u64::from_le_bytes(*b"IPCBUS\0\x01");
+/// Bumped whenever the in-segment layout changes incompatibly.
+pub const LAYOUT_VERSION: u32 = 23;
+
+/// Pending words are spaced one cache line apart.
+pub const FLAG_PADDED_PENDING: u32 = 1 << 0;
+
+/// Fixed prefix of every segment, describing how the rest of it is laid out.
+#[repr(C)]
+pub struct JournalHeader {
+    pub magic: u64,
+    pub layout_version: u32,
+    pub flags: u32,
+    pub num_topics: u32,
+    /// Ring slots in the arena: every topic's depth (its channel's `channel_slots`,
+    /// or 1 if coalesced) summed.
+    pub total_slots: u32,
+    /// `MAX_DATA_SIZE` of the build that created the segment.
+    pub data_size: u32,
+    /// Nonzero once `Bus::shutdown` has been called.
+    pub shutting_down: u32,
+    /// Payload capacity of every slot, per channel; this sizes the slot arena.
+    pub channel_data_size: [u32; NUM_CHANNELS],
+    /// Ring depth of every non-coalesced topic, per channel.
+    pub channel_slots: [u32; NUM_CHANNELS],
+    /// Number of live `Bus` handles mapping the segment, across all processes.
+    pub attached: u32,
+    /// Pid of the `create_exclusive` owner (0 if created some other way), which
+    /// `reap_stale` checks for liveness.
+    pub owner_pid: u32,
+    /// Topics whose ring is a single slot (bit `t % 64` of word `t / 64`).
+    pub coalesce: [u64; BITWORDS],
+    /// Nonzero id of this incarnation of the segment: the creator's wall clock in
+    /// nanoseconds, so it differs (and grows) each time the name is recreated.
+    pub generation: u64,
+    /// Alignment of every slot's payload within the segment (`BusConfig::payload_align`).
+    pub payload_align: u32,
+    /// `TOPICS` of the `SizedBus` that created the segment, which sizes the arrays
+    /// of its `Journal` prefix.
+    pub topic_capacity: u32,
+    /// Longest `BusConfig::post_window` of any read-write handle that has attached,
+    /// in nanoseconds: how long a post may be held back, so how long a waiter sleeps
+    /// before looking at the pending bits itself. Zero if none set one.
+    pub post_window_nanos: u64,
+}
+
+impl JournalHeader {
+    /// Fill in a freshly zeroed header and publish it by storing `magic` last.
+    pub(crate) fn init(&mut self, config: &BusConfig, topic_capacity: usize, total_slots: usize) {
+        self.layout_version = LAYOUT_VERSION;
+        self.flags = if config.pad_pending { FLAG_PADDED_PENDING } else { 0 };
+        self.num_topics = config.num_topics as u32;
+        self.total_slots = total_slots as u32;
+        self.data_size = MAX_DATA_SIZE as u32;
+        self.channel_data_size = config.channel_data_size;
+        self.channel_slots = config.channel_slots;
+        self.coalesce = core::array::from_fn(|w| config.coalesce.word(w));
+        self.generation = fresh_generation();
+        self.payload_align = config.payload_align;
+        self.topic_capacity = topic_capacity as u32;
+        unsafe { as_atomic(&self.magic) }.store(MAGIC, Ordering::Release);
+    }
+
+    /// True once the creator has finished `init`.
+    pub(crate) fn is_ready(&self) -> bool {
+        unsafe { as_atomic(&self.magic) }.load(Ordering::Acquire) == MAGIC
+    }
+
+    #[inline]
+    fn atomic(field: &u32) -> &AtomicU32 {
+        unsafe { as_atomic_u32(field) }
+    }
+
+    pub(crate) fn attach(&self) {
+        Self::atomic(&self.attached).fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub(crate) fn detach(&self) {
+        Self::atomic(&self.attached).fetch_sub(1, Ordering::AcqRel);
+    }
+
+    /// Live handles on the segment (a crashed process's handle is never released).
+    pub fn attached(&self) -> u32 {
+        Self::atomic(&self.attached).load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_owner_pid(&self, pid: u32) {
+        Self::atomic(&self.owner_pid).store(pid, Ordering::Release);
+    }
+
+    pub fn owner_pid(&self) -> u32 {
+        Self::atomic(&self.owner_pid).load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_shutting_down(&self) {
+        Self::atomic(&self.shutting_down).store(1, Ordering::Release);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        Self::atomic(&self.shutting_down).load(Ordering::Acquire) != 0
+    }
+
+    /// Raise `post_window_nanos` to `window` if it is longer.
+    pub(crate) fn widen_post_window(&self, window: Duration) {
+        let nanos = window.as_nanos().min(u64::MAX as u128) as u64;
+        unsafe { as_atomic(&self.post_window_nanos) }.fetch_max(nanos, Ordering::AcqRel);
+    }
+
+    /// The longest post window set on the segment, if any handle set one.
+    pub fn post_window(&self) -> Option<Duration> {
+        let nanos = unsafe { as_atomic(&self.post_window_nanos) }.load(Ordering::Acquire);
+        (nanos != 0).then(|| Duration::from_nanos(nanos))
+    }
+
+    /// This segment's incarnation (see `generation`).
+    pub fn generation(&self) -> u64 {
+        unsafe { as_atomic(&self.generation) }.load(Ordering::Relaxed)
+    }
+
+    /// True if this header describes a layout this build can map: its own version,
+    /// and a topic count within the `NUM_TOPICS` its fixed arrays are sized for.
+    pub fn is_compatible(&self) -> bool {
+        self.layout_version == LAYOUT_VERSION
+            && (1..=NUM_TOPICS).contains(&(self.num_topics as usize))
+            && self.data_size as usize == MAX_DATA_SIZE
+            && self.channel_data_size.iter().all(|&n| n > 0 && n as usize <= MAX_DATA_SIZE)
+            && self.channel_slots.iter().all(|&n| n > 0 && n as usize <= MAX_SLOTS_PER_TOPIC)
+            && self.payload_align.is_power_of_two()
+            && (8..=MAX_PAYLOAD_ALIGN).contains(&(self.payload_align as usize))
+    }
+
+    /// True if a `SizedBus` with these dimensions can map the segment: its journal
+    /// prefix was laid out for `topics`, and no ring or slot is larger than allowed.
+    pub(crate) fn fits(&self, topics: usize, slots: usize, data: usize) -> bool {
+        self.topic_capacity as usize == topics
+            && self.num_topics as usize <= topics
+            && self.channel_slots.iter().all(|&n| n as usize <= slots)
+            && self.channel_data_size.iter().all(|&n| n as usize <= data)
+    }
+}
+
+fn fresh_generation() -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    (now.as_nanos() as u64).max(1)
+}
@@ -0,0 +1,151 @@
+//! `Backing::Heap`: segments allocated in this process, for tests.
+//!
+//! A process-wide table maps bus names to heap allocations, so every `Bus` opened
+//! with the same name in the process shares one journal, just as separate
+//! mappings of a shm object do. Each segment carries one Condvar-based semaphore
+//! per group in place of the named primitive, so nothing is created in the OS.
+//! Unlinking removes the name from the table; open handles keep the memory alive.
+
+use std::alloc::{alloc_zeroed, dealloc, Layout};
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+use crate::notify::Notifier;
+use crate::{BusError, MAX_PAYLOAD_ALIGN, NUM_GROUPS};
+
+/// Heap-backed segments by bus name.
+fn segments() -> MutexGuard<'static, HashMap<String, Arc<HeapSegment>>> {
+    static SEGMENTS: OnceLock<Mutex<HashMap<String, Arc<HeapSegment>>>> = OnceLock::new();
+    SEGMENTS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// One zeroed allocation standing in for a shm object, plus its groups' semaphores.
+pub(crate) struct HeapSegment {
+    ptr: *mut u8,
+    layout: Layout,
+    sems: [HeapSem; NUM_GROUPS],
+}
+
+// The journal is only ever accessed through atomics, like a shared mapping.
+unsafe impl Send for HeapSegment {}
+unsafe impl Sync for HeapSegment {}
+
+impl HeapSegment {
+    fn new(size: usize) -> Self {
+        // Page-aligned like a real mapping, so `payload_align` holds in the heap too.
+        let layout = Layout::from_size_align(size.max(1), MAX_PAYLOAD_ALIGN).expect("segment size overflows");
+        let ptr = unsafe { alloc_zeroed(layout) };
+        if ptr.is_null() {
+            std::alloc::handle_alloc_error(layout);
+        }
+        Self { ptr, layout, sems: Default::default() }
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.layout.size()
+    }
+}
+
+impl Drop for HeapSegment {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.ptr, self.layout) };
+    }
+}
+
+/// Create segment `name` with `size` bytes (if `create`), or look up the existing
+/// one. Returns it and whether this call created it.
+pub(crate) fn map(name: &str, size: usize, create: bool) -> Result<(Arc<HeapSegment>, bool), BusError> {
+    let mut segments = segments();
+    if let Some(segment) = segments.get(name) {
+        return Ok((segment.clone(), false));
+    }
+    if !create {
+        return Err(BusError::NotFound);
+    }
+    let segment = Arc::new(HeapSegment::new(size));
+    segments.insert(name.to_owned(), segment.clone());
+    Ok((segment, true))
+}
+
+/// Forget segment `name`; handles that still have it keep working.
+pub(crate) fn unlink(name: &str) {
+    segments().remove(name);
+}
+
+/// Group `group`'s semaphore on segment `name`.
+pub(crate) fn semaphore(name: &str, group: usize) -> std::io::Result<HeapNotifier> {
+    let segment = segments()
+        .get(name)
+        .cloned()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no heap segment with this name"))?;
+    Ok(HeapNotifier { segment, group })
+}
+
+/// Counting semaphore on a Mutex and Condvar.
+#[derive(Default)]
+struct HeapSem {
+    tokens: Mutex<u32>,
+    posted: Condvar,
+}
+
+/// A `Notifier` on one of a heap segment's semaphores; holds the segment alive.
+pub(crate) struct HeapNotifier {
+    segment: Arc<HeapSegment>,
+    group: usize,
+}
+
+impl HeapNotifier {
+    fn sem(&self) -> &HeapSem {
+        &self.segment.sems[self.group]
+    }
+
+    fn tokens(&self) -> MutexGuard<'_, u32> {
+        self.sem().tokens.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Notifier for HeapNotifier {
+    fn post(&self) {
+        *self.tokens() += 1;
+        self.sem().posted.notify_one();
+    }
+
+    fn wait(&self) {
+        let mut tokens = self.tokens();
+        while *tokens == 0 {
+            tokens = self.sem().posted.wait(tokens).unwrap_or_else(|e| e.into_inner());
+        }
+        *tokens -= 1;
+    }
+
+    fn try_wait(&self) -> bool {
+        let mut tokens = self.tokens();
+        let took = *tokens > 0;
+        if took {
+            *tokens -= 1;
+        }
+        took
+    }
+
+    fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (mut tokens, _) = self
+            .sem()
+            .posted
+            .wait_timeout_while(self.tokens(), timeout, |tokens| *tokens == 0)
+            .unwrap_or_else(|e| e.into_inner());
+        let took = *tokens > 0;
+        if took {
+            *tokens -= 1;
+        }
+        took
+    }
+
+    fn value(&self) -> Option<i32> {
+        Some((*self.tokens()).min(i32::MAX as u32) as i32)
+    }
+}
@@ -0,0 +1,115 @@
+//! Payloads bigger than a slot, split across consecutive seqs of one topic.
+//!
+//! Each fragment's payload starts with an 8-byte header:
+//!
+//! ```text
+//! fragment_index u16 | fragment_count u16 | total_len u32   (little-endian)
+//! ```
+//!
+//! Fragment `i` of a message starting at seq `s` is published at seq `s + i`
+//! (skipping `EMPTY_SEQ` on wrap), so a reader can tell from the seqs alone whether
+//! the set is still intact.
+
+use crate::{next_seq, BusError, Message, SizedBus, EMPTY_SEQ, MAX_DATA_SIZE};
+
+const FRAGMENT_HEADER_SIZE: usize = 8;
+
+struct FragmentHeader {
+    index: u16,
+    count: u16,
+    total_len: u32,
+}
+
+impl FragmentHeader {
+    fn encode(&self) -> [u8; FRAGMENT_HEADER_SIZE] {
+        let mut out = [0u8; FRAGMENT_HEADER_SIZE];
+        out[0..2].copy_from_slice(&self.index.to_le_bytes());
+        out[2..4].copy_from_slice(&self.count.to_le_bytes());
+        out[4..8].copy_from_slice(&self.total_len.to_le_bytes());
+        out
+    }
+
+    fn decode(payload: &[u8]) -> Option<(Self, &[u8])> {
+        let (head, body) = payload.split_at_checked(FRAGMENT_HEADER_SIZE)?;
+        let header = Self {
+            index: u16::from_le_bytes([head[0], head[1]]),
+            count: u16::from_le_bytes([head[2], head[3]]),
+            total_len: u32::from_le_bytes([head[4], head[5], head[6], head[7]]),
+        };
+        (header.index < header.count).then_some((header, body))
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Largest payload `write_large` accepts on `topic_id`: one fragment per ring slot,
+    /// so the first fragment is still resident when the last one lands.
+    pub fn max_large_payload(&self, topic_id: usize) -> usize {
+        let per_fragment = self.capacity(topic_id).saturating_sub(FRAGMENT_HEADER_SIZE);
+        let depth = self.layout.topics[topic_id].depth.min(u16::MAX as usize);
+        per_fragment * depth
+    }
+
+    /// Store `data` as fragments at seqs `seq, seq + 1, ...` and return the seq of the
+    /// last one (the next message on the topic should use one past it). Like `write`,
+    /// it doesn't notify, and writes nothing if `data` exceeds `max_large_payload`
+    /// (always, on a channel too small to hold a fragment header).
+    ///
+    /// Topics carrying fragmented messages should be read with `read_large`, since a
+    /// plain `read` returns each fragment with its header.
+    pub fn write_large(&self, topic_id: usize, seq: u64, data: &[u8]) -> Result<u64, BusError> {
+        let max = self.max_large_payload(topic_id);
+        let per_fragment = self.capacity(topic_id).saturating_sub(FRAGMENT_HEADER_SIZE);
+        if data.len() > max || per_fragment == 0 {
+            return Err(BusError::PayloadTooLarge { len: data.len(), max });
+        }
+        let count = data.len().div_ceil(per_fragment).max(1);
+
+        let mut buf = [0u8; MAX_DATA_SIZE];
+        let mut fragment_seq = seq;
+        for index in 0..count {
+            if index > 0 {
+                fragment_seq = next_seq(fragment_seq);
+            }
+            let body = &data[(index * per_fragment).min(data.len())..((index + 1) * per_fragment).min(data.len())];
+            let header =
+                FragmentHeader { index: index as u16, count: count as u16, total_len: data.len() as u32 };
+            buf[..FRAGMENT_HEADER_SIZE].copy_from_slice(&header.encode());
+            buf[FRAGMENT_HEADER_SIZE..FRAGMENT_HEADER_SIZE + body.len()].copy_from_slice(body);
+            self.write(topic_id, fragment_seq, &buf[..FRAGMENT_HEADER_SIZE + body.len()])?;
+        }
+        Ok(fragment_seq)
+    }
+
+    /// Reassemble the fragmented message that `seq` belongs to; any of its fragment seqs
+    /// works, so a consumer can pass `get_latest_seq` straight in. Returns None if a
+    /// fragment has been overwritten, isn't published yet, or doesn't belong to the set.
+    pub fn read_large(&self, topic_id: usize, seq: u64) -> Option<Vec<u8>> {
+        let msg = self.read(topic_id, seq)?;
+        let (header, _) = FragmentHeader::decode(msg.payload())?;
+        let first = match seq.wrapping_sub(header.index as u64) {
+            // The fragments wrapped past EMPTY_SEQ, which holds none of them.
+            first if header.index > 0 && (first == EMPTY_SEQ || first > seq) => first.wrapping_sub(1),
+            first => first,
+        };
+
+        let mut data = Vec::with_capacity(header.total_len as usize);
+        let mut fragment = Message::default();
+        let mut fragment_seq = first;
+        for index in 0..header.count {
+            if index > 0 {
+                fragment_seq = next_seq(fragment_seq);
+            }
+            if !self.read_into(topic_id, fragment_seq, &mut fragment) {
+                return None;
+            }
+            let (h, body) = FragmentHeader::decode(fragment.payload())?;
+            if h.index != index || h.count != header.count || h.total_len != header.total_len {
+                return None;
+            }
+            data.extend_from_slice(body);
+        }
+        (data.len() == header.total_len as usize).then_some(data)
+    }
+}
@@ -0,0 +1,64 @@
+//! Where each topic's ring lives in the slot arena.
+//!
+//! The arena follows the fixed `Journal` prefix (sized by the bus's dimensions),
+//! with the header's `num_topics` topics back to back. A topic's slots share one
+//! stride, its channel's payload capacity plus the `SlotHeader` rounded up to the
+//! payload alignment, and the first is placed so every payload starts aligned. A
+//! ring is as deep as its channel's depth, or one slot if coalesced. Every process
+//! derives the same table from the header.
+
+use crate::slot::SLOT_HEADER_SIZE;
+use crate::NUM_CHANNELS;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TopicLayout {
+    /// The topic's id, its index in `Layout::topics`.
+    pub id: usize,
+    /// Byte offset of the topic's first slot from the start of the segment.
+    pub offset: usize,
+    /// Number of slots in the ring.
+    pub depth: usize,
+    /// Payload bytes a slot can hold.
+    pub capacity: usize,
+    /// Bytes from one slot to the next.
+    pub stride: usize,
+}
+
+pub(crate) struct Layout {
+    pub topics: Box<[TopicLayout]>,
+    /// Slots across every ring.
+    pub slots: usize,
+    /// Total segment size in bytes.
+    pub size: usize,
+}
+
+impl Layout {
+    /// The layout of a segment whose `Journal` prefix is `prefix` bytes.
+    pub(crate) fn compute(
+        prefix: usize,
+        num_topics: usize,
+        channel_data_size: &[u32; NUM_CHANNELS],
+        channel_slots: &[u32; NUM_CHANNELS],
+        coalesce: &[u64],
+        payload_align: u32,
+    ) -> Self {
+        let align = (payload_align as usize).max(8);
+        let mut offset = prefix.next_multiple_of(64);
+        let topics = (0..num_topics)
+            .map(|t| {
+                // The lowest coordinate of a topic id (see `topic_coords`), for any bus size.
+                let channel = t % NUM_CHANNELS;
+                let capacity = channel_data_size[channel] as usize;
+                let stride = (SLOT_HEADER_SIZE + capacity).next_multiple_of(align);
+                offset = (offset + SLOT_HEADER_SIZE).next_multiple_of(align) - SLOT_HEADER_SIZE;
+                let coalesced = coalesce.get(t / 64).is_some_and(|&w| w & (1 << (t % 64)) != 0);
+                let depth = if coalesced { 1 } else { channel_slots[channel] as usize };
+                let topic = TopicLayout { id: t, offset, depth, capacity, stride };
+                offset += topic.depth * stride;
+                topic
+            })
+            .collect::<Box<[TopicLayout]>>();
+        let slots = topics.iter().map(|t| t.depth).sum();
+        Self { topics, slots, size: offset }
+    }
+}
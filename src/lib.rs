@@ -1,190 +1,1706 @@
-use core::sync::atomic::{AtomicU64, Ordering};
-use shared_memory::{Shmem, ShmemConf};
-use std::ffi::CString;
+use core::sync::atomic::{fence, AtomicU16, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use core::time::Duration;
+use std::sync::{Arc, OnceLock};
+
+use backing::SharedMapping;
+use layout::{Layout, TopicLayout};
+use notify::Notifier;
+use slot::SlotHeader;
+use window::PostWindow;
+
+mod ack;
+mod backing;
+mod bench;
+pub mod cbus;
+mod claim;
+mod clock;
+#[cfg(feature = "lz4")]
+mod compress;
+mod config;
+mod crc;
+#[cfg(feature = "crypto")]
+mod crypto;
+mod endpoint;
+mod error;
+mod group;
+mod guard;
+mod header;
+#[cfg(feature = "testing")]
+mod heap;
+mod large;
+mod layout;
+mod mask;
+mod notify;
+mod ordering;
+mod owned;
+mod payload;
+mod rate;
+mod readonly;
+mod record;
+mod registry;
+mod schedule;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod slot;
+mod stats;
+mod topic;
+mod window;
+#[cfg(feature = "tokio")]
+mod stream;
+
+pub use ack::{Acks, MAX_CONSUMERS};
+pub use backing::Backing;
+pub use bench::ThroughputReport;
+pub use claim::ProducerToken;
+pub use clock::{monotonic_nanos, Clock, MockClock, MonotonicClock};
+pub use config::BusConfig;
+pub use crc::crc32;
+#[cfg(feature = "crypto")]
+pub use crypto::PayloadKey;
+pub use endpoint::{Consumer, Producer, Subscription};
+pub use error::{BusError, IntegrityError, PayloadOverrun};
+pub use group::{Groups, NUM_GROUPS};
+pub use guard::WriteGuard;
+pub use header::{JournalHeader, FLAG_PADDED_PENDING, LAYOUT_VERSION, MAGIC};
+pub use mask::{SubscriptionMask, Topics};
+pub use notify::NotifyBackend;
+pub use ordering::OrderingProfile;
+pub use owned::OwnedBus;
+pub use payload::{PayloadReader, PayloadWriter};
+pub use rate::RateLimiter;
+pub use readonly::ReadOnlyBus;
+pub use registry::{Registry, RegistryEntry, MAX_TOPIC_NAME_LEN};
+pub use record::{Player, Record, Recorder, RecordingHeader};
+pub use schedule::DrainSchedule;
+pub use slot::SEQLOCK_RETRIES;
+pub use stats::{ExchangeStats, GroupStats, Stats, StatsSnapshot, TopicStats};
+pub use topic::Topic;
+#[cfg(feature = "tokio")]
+pub use stream::BusStream;
 
 pub const NUM_EXCHANGES: usize = 5;
 pub const NUM_MARKETS: usize = 2;
 pub const NUM_SYMBOLS: usize = 30;
 pub const NUM_CHANNELS: usize = 2;
 pub const NUM_TOPICS: usize = NUM_EXCHANGES * NUM_MARKETS * NUM_SYMBOLS * NUM_CHANNELS; // 600
+/// Default ring depth; see `BusConfig::channel_slots`.
 pub const SLOTS_PER_TOPIC: usize = 10;
-pub const JOURNAL_SIZE: usize = NUM_TOPICS * SLOTS_PER_TOPIC;
+/// Largest `BusConfig::payload_align`: a page, the alignment of the mapping itself.
+pub const MAX_PAYLOAD_ALIGN: usize = 4096;
+/// Deepest ring a channel can be configured with.
+pub const MAX_SLOTS_PER_TOPIC: usize = 1 << 16;
+/// Default payload capacity of a slot; see `BusConfig::channel_data_size`.
 pub const DATA_SIZE: usize = 1024;
+/// Largest payload capacity a channel can be configured with, and the size of `Message::data`.
+pub const MAX_DATA_SIZE: usize = 4096;
 
-pub const BITWORDS: usize = (NUM_TOPICS + 63) / 64; // 600 -> 10 u64 words
+pub const BITWORDS: usize = NUM_TOPICS.div_ceil(64); // 600 -> 10 u64 words
 
-#[repr(C)]
+/// u64s per cache line; the pending region reserves this many per word so the
+/// padded layout fits without changing the segment size.
+pub const CACHE_LINE_WORDS: usize = 64 / core::mem::size_of::<u64>();
+
+/// Sequence number reserved as the "empty" sentinel: a freshly zeroed slot has seq 0,
+/// so producers start at 1 and never publish seq 0. `read` never returns it.
+pub const EMPTY_SEQ: u64 = 0;
+
+/// True if `a` comes after `b` in wrapping seq order: `a` is less than 2^63 steps
+/// ahead of `b`, so `seq_newer(1, u64::MAX)` holds (like TCP sequence numbers).
+/// Every ordering of seqs on the bus goes through this, never a bare `>`.
+#[inline]
+pub const fn seq_newer(a: u64, b: u64) -> bool {
+    (a.wrapping_sub(b) as i64) > 0
+}
+
+/// View a u64 living in the mapping as an AtomicU64 (same size and alignment).
+#[inline]
+pub(crate) unsafe fn as_atomic<'a>(word: *const u64) -> &'a AtomicU64 {
+    unsafe { &*(word as *const AtomicU64) }
+}
+
+/// `as_atomic` for the mapping's u32 fields.
+#[inline]
+pub(crate) unsafe fn as_atomic_u32<'a>(word: *const u32) -> &'a AtomicU32 {
+    unsafe { &*(word as *const AtomicU32) }
+}
+
+/// u16 counterpart of `as_atomic`.
+#[inline]
+pub(crate) unsafe fn as_atomic_u16<'a>(word: *const u16) -> &'a AtomicU16 {
+    unsafe { &*(word as *const AtomicU16) }
+}
+
+/// u8 counterpart of `as_atomic`.
+#[inline]
+pub(crate) unsafe fn as_atomic_u8<'a>(byte: *const u8) -> &'a AtomicU8 {
+    unsafe { &*(byte as *const AtomicU8) }
+}
+
+/// Aligned to 64 bytes with `data` first, so a copied message's payload can be
+/// loaded with aligned SIMD vectors wherever the `Message` itself lives.
+#[repr(C, align(64))]
 #[derive(Clone, Debug, Copy)]
 pub struct Message {
+    pub data: [u8; MAX_DATA_SIZE],
     pub seq: u64,
-    pub data: [u8; DATA_SIZE],
+    /// The bus clock (`BusConfig::clock`, `monotonic_nanos()` by default) at publish
+    /// time, or the caller's own stamp via `write_at`.
+    pub publish_ts_nanos: u64,
     pub data_len: u32,
+    /// CRC-32 of `data[..data_len]`, computed by `write`.
+    pub crc: u32,
+    /// Caller-defined event type set by `write_typed`, e.g. snapshot vs. update on
+    /// one channel; 0 for messages stored with plain `write`.
+    pub msg_type: u16,
+    /// Version of the payload's encoding from `write_versioned`; 0 otherwise. Lets
+    /// consumers keep parsing the old format while producers roll over to a new one.
+    pub schema_version: u8,
+    /// The payload is an LZ4 block from `write_compressed`; `decompressed` (feature
+    /// `lz4`) inflates it. The CRC covers the stored, compressed bytes.
+    pub compressed: bool,
 }
 
 impl Message {
+    /// Message carrying `data` (at most MAX_DATA_SIZE bytes), with its CRC filled in.
     #[inline]
-    pub fn get_text(&self) -> &str {
-        unsafe { std::str::from_utf8_unchecked(&self.data[..self.data_len as usize]) }
+    fn with_payload(seq: u64, data: &[u8], publish_ts_nanos: u64) -> Self {
+        let len = data.len();
+        let mut msg = Message { seq, publish_ts_nanos, data_len: len as u32, ..Message::default() };
+        msg.data[..len].copy_from_slice(data);
+        msg.crc = crc32(data);
+        msg
+    }
+
+    /// The payload as text, or an error if it isn't valid UTF-8. The bytes come from
+    /// whichever process wrote the slot, so they are checked rather than trusted.
+    #[inline]
+    pub fn try_get_text(&self) -> Result<&str, core::str::Utf8Error> {
+        core::str::from_utf8(self.payload())
+    }
+
+    /// The payload bytes, `data[..data_len]` (clamped to MAX_DATA_SIZE).
+    #[inline]
+    pub fn payload(&self) -> &[u8] {
+        &self.data[..(self.data_len as usize).min(MAX_DATA_SIZE)]
+    }
+
+    /// True if the stored CRC matches the payload.
+    #[inline]
+    pub fn crc_ok(&self) -> bool {
+        crc32(self.payload()) == self.crc
+    }
+
+    /// Time since the message was published, on the monotonic clock. Only right for
+    /// buses on the default `MonotonicClock`; `Bus::age` uses the bus's own clock.
+    #[inline]
+    pub fn age(&self) -> Duration {
+        Duration::from_nanos(monotonic_nanos().saturating_sub(self.publish_ts_nanos))
     }
 }
 
+/// Messages compare by their meaningful contents; bytes past `data_len` are ignored.
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.seq == other.seq
+            && self.publish_ts_nanos == other.publish_ts_nanos
+            && self.crc == other.crc
+            && self.msg_type == other.msg_type
+            && self.schema_version == other.schema_version
+            && self.compressed == other.compressed
+            && self.payload() == other.payload()
+    }
+}
+
+impl Eq for Message {}
+
 impl Default for Message {
     fn default() -> Self {
-        Message { seq: 0, data: [0u8; DATA_SIZE], data_len: 0 }
+        Message {
+            seq: 0,
+            publish_ts_nanos: 0,
+            data: [0u8; MAX_DATA_SIZE],
+            data_len: 0,
+            crc: 0,
+            msg_type: 0,
+            schema_version: 0,
+            compressed: false,
+        }
+    }
+}
+
+/// What `Bus::read_status` found in the slot a seq maps to.
+// Unboxed like the `Option<Message>` from `read`; a heap allocation per read would cost more.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlotStatus {
+    /// The slot holds the requested seq.
+    Present(Message),
+    /// A newer message has reused the slot; `current_seq` is the one there now.
+    Overwritten { current_seq: u64 },
+    /// The slot holds nothing newer than the requested seq yet.
+    Future,
+}
+
+/// How a `Bus::wait_and_drain_mask_timeout` call ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrainOutcome {
+    /// Woken by a notification; this many subscribed topics were handed to the callback
+    /// (0 if the wakeup was only for topics outside the mask).
+    Drained(usize),
+    /// The timeout elapsed with no notification.
+    TimedOut,
+}
+
+/// What `Bus::read_bounded` got within its attempt budget.
+// Unboxed for the same reason as `SlotStatus`.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReadOutcome {
+    /// A stable copy of the requested seq, after `retries` torn or mid-write attempts.
+    Read { msg: Message, retries: usize },
+    /// The slot was read cleanly but doesn't hold that seq: never written, already
+    /// overwritten, or not published yet.
+    Missing,
+    /// The producer kept the slot mid-write for every attempt.
+    Contended,
+}
+
+/// Zero-copy view of a resident message, borrowing the slot in the mapping.
+///
+/// The producer may recycle the slot at any time, so the borrowed bytes are only
+/// trustworthy if `is_current()` still returns true *after* you are done reading them
+/// (the usual seqlock read protocol). Copy via `Bus::read` if you can't tolerate that.
+#[derive(Clone, Copy, Debug)]
+pub struct MessageRef<'a> {
+    pub seq: u64,
+    pub publish_ts_nanos: u64,
+    pub msg_type: u16,
+    /// See `Message::schema_version`.
+    pub schema_version: u8,
+    /// See `Message::compressed`.
+    pub compressed: bool,
+    data: &'a [u8],
+    version: &'a AtomicU64,
+    observed: u64,
+}
+
+impl<'a> MessageRef<'a> {
+    /// The payload, `data[..data_len]`, in place in shared memory.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// True if the slot hasn't been rewritten since this view was taken.
+    #[inline]
+    pub fn is_current(&self) -> bool {
+        fence(Ordering::Acquire);
+        self.version.load(Ordering::Relaxed) == self.observed
+    }
+}
+
+/// Backing store for the pending bitmap, cache-line aligned so the padded layout
+/// really gives every word its own line.
+#[repr(C, align(64))]
+pub struct PendingBits<const WORDS: usize = BITWORDS>(pub [[u64; CACHE_LINE_WORDS]; WORDS]);
+
+impl<const WORDS: usize> PendingBits<WORDS> {
+    /// The `i`th u64 of the region, counting across lines.
+    #[inline]
+    pub(crate) fn word(&self, i: usize) -> &u64 {
+        &self.0.as_flattened()[i]
     }
 }
 
+/// Fixed prefix of the segment, for a bus of at most `TOPICS` topics (`WORDS` is
+/// `TOPICS.div_ceil(64)`, the bitmap length). The slot arena follows it, sized
+/// from the header's `channel_data_size` table.
 #[repr(C)]
-pub struct Journal {
-    pub slots: [Message; JOURNAL_SIZE],
-    /// Atomic bitmap split into BITWORDS 64-bit words.
-    /// Each bit i corresponds to topic_id i (0..NUM_TOPICS-1).
+pub struct Journal<const TOPICS: usize = NUM_TOPICS, const WORDS: usize = BITWORDS> {
+    pub header: JournalHeader,
+    /// `[tokens, waiters]` per notification group for `NotifyBackend::Futex`. Ahead
+    /// of the sized arrays, so every `Journal` has it at the same offset.
+    pub futex: [[u32; 2]; NUM_GROUPS],
+    /// Atomic bitmap split into WORDS 64-bit words.
+    /// Each bit i corresponds to topic_id i (0..TOPICS-1).
+    /// Word w lives at index `w` (compact) or `w * CACHE_LINE_WORDS` (FLAG_PADDED_PENDING).
     /// NOTE: We store as raw u64s to keep repr(C) predictable; we use AtomicU64 via pointer casts.
-    pub pending_bits: [u64; BITWORDS],
+    pub pending_bits: PendingBits<WORDS>,
+    pub stats: Stats<TOPICS>,
+    pub acks: Acks<TOPICS>,
+    /// Pending bitmaps and masks of consumer groups `1..NUM_GROUPS`.
+    pub groups: Groups<WORDS>,
+    /// Bit per topic, set by its first write and cleared only by `clear_topic`.
+    pub active_bits: [u64; WORDS],
+    /// Topic names from `register_topic`.
+    pub registry: Registry<TOPICS>,
+    /// Pid holding each topic's `claim_producer` claim; 0 if unclaimed.
+    pub producer_pid: [u32; TOPICS],
 }
 
-unsafe impl Send for Bus {}
+unsafe impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize> Send
+    for SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+}
+// Every method takes &self and touches the mapping only through atomics or the
+// seqlock, so sharing a Bus between threads is as safe as sharing it between processes.
+unsafe impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize> Sync
+    for SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+}
+
+/// The bus with the crate's default dimensions: `NUM_TOPICS` topics, rings and
+/// payloads up to `MAX_SLOTS_PER_TOPIC` and `MAX_DATA_SIZE`.
+pub type Bus = SizedBus<NUM_TOPICS, MAX_SLOTS_PER_TOPIC, MAX_DATA_SIZE, BITWORDS>;
 
-pub struct Bus {
-    pub shmem: Option<Shmem>,
-    pub journal: *mut Journal,
+/// A bus handle whose journal is sized by its parameters: at most `TOPICS` topics
+/// (`WORDS` must be `TOPICS.div_ceil(64)`), rings at most `SLOTS` deep and payloads
+/// of at most `DATA` bytes. A creator's config is capped to them; an attacher fails
+/// with `IncompatibleLayout` if the segment was created for other dimensions.
+/// Every process on a bus has to use the same `TOPICS`.
+pub struct SizedBus<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize> {
+    /// Keeps the segment mapped; `journal` points into it. Read-write handles on
+    /// one bus in this process share it (see `backing::map_shared`).
+    _mapping: Arc<SharedMapping>,
+    /// Mapped `PROT_READ`: nothing may store to the segment, not even the attach count.
+    read_only: bool,
+    /// Counted in the header's `attached`, so dropping the handle detaches it.
+    attached: bool,
+    pub journal: *mut Journal<TOPICS, WORDS>,
+    /// Distance in u64s between consecutive pending words (from the header flags).
+    pending_stride: usize,
+    /// Pending words in use: one per 64 of the header's `num_topics`.
+    pending_words: usize,
+    /// Where each topic's slots live, derived from the header.
+    layout: Layout,
+    /// Ack row claimed by `register_consumer`, or `ack::NO_CONSUMER`.
+    consumer: AtomicUsize,
+    /// Topic `drain_round_robin` starts its next pass at.
+    drain_cursor: AtomicUsize,
 
-    // POSIX named semaphore for notifications
-    sem: *mut libc::sem_t,
+    // Named semaphore (POSIX) / auto-reset Event (Windows) for notifications
+    notifier: Arc<dyn Notifier>,
+    /// Consumer group whose bitmap and semaphore this handle waits on; 0 by default.
+    group: usize,
+    /// Other groups' semaphores, opened the first time this handle notifies them.
+    group_notifiers: [OnceLock<Option<Box<dyn Notifier>>>; NUM_GROUPS],
+    /// Posts held back by `BusConfig::post_window`.
+    post_window: PostWindow,
+    /// Bus name and config, kept to open `group_notifiers` lazily.
+    name: String,
+    config: BusConfig,
 }
 
-impl Bus {
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Compute a flat topic_id from (exchange, market, symbol, channel). Panics if a
+    /// coordinate is out of range, rather than returning some other topic's id.
+    #[inline]
+    pub const fn topic_id(exchange: usize, market: usize, symbol: usize, channel: usize) -> usize {
+        assert!(exchange < NUM_EXCHANGES, "exchange out of range");
+        assert!(market < NUM_MARKETS, "market out of range");
+        assert!(symbol < NUM_SYMBOLS, "symbol out of range");
+        assert!(channel < NUM_CHANNELS, "channel out of range");
+
+        ((exchange * NUM_MARKETS + market) * NUM_SYMBOLS + symbol) * NUM_CHANNELS + channel
+    }
+
+    /// Inverse of `topic_id`: decode a flat topic_id into (exchange, market, symbol, channel)
+    #[inline]
+    pub const fn topic_coords(topic_id: usize) -> (usize, usize, usize, usize) {
+        debug_assert!(topic_id < NUM_TOPICS);
+
+        let channel = topic_id % NUM_CHANNELS;
+        let rest = topic_id / NUM_CHANNELS;
+        let symbol = rest % NUM_SYMBOLS;
+        let rest = rest / NUM_SYMBOLS;
+        let market = rest % NUM_MARKETS;
+        let exchange = rest / NUM_MARKETS;
+        (exchange, market, symbol, channel)
+    }
+
+    /// Map an existing bus `PROT_READ`, for monitors that must not be able to corrupt it.
+    pub fn open_readonly(name: &str) -> Result<ReadOnlyBus<TOPICS, SLOTS, DATA, WORDS>, BusError> {
+        Self::open_readonly_with(name, &BusConfig::default())
+    }
+
+    /// `open_readonly` for a bus with a non-default backing or semaphore prefix.
+    /// Refuses `NotifyBackend::Futex`, whose waits store to the segment.
+    pub fn open_readonly_with(
+        name: &str,
+        config: &BusConfig,
+    ) -> Result<ReadOnlyBus<TOPICS, SLOTS, DATA, WORDS>, BusError> {
+        #[cfg(target_os = "linux")]
+        if config.notify_backend == NotifyBackend::Futex {
+            return Err(BusError::Notifier("the futex backend needs a writable mapping".into()));
+        }
+        Self::open_inner(name, config, OpenMode::ReadOnly, 0).map(ReadOnlyBus::new)
+    }
+
     pub fn open_or_create(name: &str) -> Self {
-        const MEM_SIZE: usize = core::mem::size_of::<Journal>();
-
-        let (shmem, created) = match ShmemConf::new().size(MEM_SIZE).os_id(name).create() {
-            Ok(s) => (s, true),
-            Err(_) => (
-                ShmemConf::new().size(MEM_SIZE).os_id(name).open()
-                    .expect("Cannot create/open shared memory"),
-                false
-            ),
+        Self::open_or_create_with(name, &BusConfig::default())
+    }
+
+    /// Fallible `open_or_create`.
+    pub fn try_open_or_create(name: &str) -> Result<Self, BusError> {
+        Self::try_open_or_create_with(name, &BusConfig::default())
+    }
+
+    /// Like `open_or_create`, applying `config` if this call creates the segment.
+    pub fn open_or_create_with(name: &str, config: &BusConfig) -> Self {
+        Self::try_open_or_create_with(name, config).unwrap_or_else(|e| panic!("bus '{}': {}", name, e))
+    }
+
+    /// Fallible `open_or_create_with`: reports a segment that can't be used (too small,
+    /// never initialized, incompatible) instead of panicking.
+    pub fn try_open_or_create_with(name: &str, config: &BusConfig) -> Result<Self, BusError> {
+        Self::open_inner(name, config, OpenMode::Create, 0)
+    }
+
+    /// Attach to a bus that already exists, failing with `BusError::NotFound` if there
+    /// is none instead of creating an empty one. A consumer started before its producer
+    /// can then fail fast or retry, rather than wait forever on a segment the producer
+    /// will never write to.
+    pub fn open(name: &str) -> Result<Self, BusError> {
+        Self::try_open(name, &BusConfig::default())
+    }
+
+    /// `open` with a config. For tools that inspect a running bus.
+    pub fn try_open(name: &str, config: &BusConfig) -> Result<Self, BusError> {
+        Self::open_inner(name, config, OpenMode::Attach, 0)
+    }
+
+    /// True if the name now refers to a different segment than the one this handle
+    /// maps, i.e. the producer unlinked and recreated it (see `reopen`). False while
+    /// nothing (or something not yet initialized) is under the name.
+    pub fn segment_replaced(&self) -> bool {
+        let Ok(current) = backing::map_readonly(&self.name, &self.config.backing) else {
+            return false;
+        };
+        if current.len() < core::mem::size_of::<JournalHeader>() {
+            return false;
+        }
+        let header = unsafe { &*(current.as_ptr() as *const JournalHeader) };
+        header.is_ready() && header.generation() != self.header().generation()
+    }
+
+    /// Drop this handle's mapping and attach again to the segment now under its name,
+    /// re-validating the header, for a consumer that outlived a producer restart
+    /// (`segment_replaced`, or heartbeats gone stale). The handle keeps its name,
+    /// config and group; a registered consumer gets a fresh ack row on the new
+    /// segment, and a group handle re-registers its mask there. On error the handle
+    /// is left on the old mapping, so the call can simply be retried.
+    ///
+    /// With `BusConfig::fixed_addr`, the new segment is mapped over the old one's
+    /// pages instead, so pointers into the mapping stay put. That needs this to be
+    /// the only read-write handle on the old segment in the process. Once the pages
+    /// are replaced the handle can't go back: if attaching then fails (the new
+    /// segment was swapped out again in between), it is left on the new pages,
+    /// unregistered.
+    pub fn reopen(&mut self) -> Result<(), BusError> {
+        let mode = if self.read_only { OpenMode::ReadOnly } else { OpenMode::Attach };
+        let registered = self.consumer.load(Ordering::Acquire) != ack::NO_CONSUMER;
+        let group_mask = self.group_mask();
+        #[cfg(unix)]
+        if self.config.fixed_addr.is_some() && !self.read_only {
+            self.remap_in_place(registered)?;
+        }
+        let fresh = Self::open_inner(&self.name, &self.config, mode, self.group)?;
+        if self.group != 0 {
+            fresh.join_group(self.group, &group_mask);
+        }
+        if registered {
+            fresh.register_consumer()?;
+        }
+        *self = fresh;
+        Ok(())
+    }
+
+    /// `reopen` for a `fixed_addr` handle, up to attaching: check the new segment,
+    /// let go of the old one's header (consumer row and attach count), then map the
+    /// new one at the same address. On error nothing has changed.
+    #[cfg(unix)]
+    fn remap_in_place(&mut self, registered: bool) -> Result<(), BusError> {
+        if Arc::strong_count(&self._mapping) > 1 {
+            return Err(BusError::Open("other handles in this process still map the old segment".into()));
+        }
+        let current = backing::map_readonly(&self.name, &self.config.backing)?;
+        let need = core::mem::size_of::<JournalHeader>();
+        if current.len() < need {
+            return Err(BusError::SegmentTooSmall { got: current.len(), need });
+        }
+        let header = unsafe { &*(current.as_ptr() as *const JournalHeader) };
+        if !header.is_ready() {
+            return Err(BusError::NotInitialized);
+        }
+        if !header.is_compatible() || !header.fits(TOPICS, SLOTS, DATA) {
+            return Err(BusError::IncompatibleLayout { layout_version: header.layout_version });
+        }
+
+        self.unregister_consumer();
+        self.header().detach();
+        if let Err(e) = self._mapping.remap(&self.name, &self.config.backing) {
+            self.header().attach();
+            if registered {
+                // The old row is free again, so this can only fail to a racing consumer.
+                let _ = self.register_consumer();
+            }
+            return Err(e);
+        }
+        self.attached = false;
+        Ok(())
+    }
+
+    /// Rejects, at compile time, dimensions no segment can be laid out for.
+    const DIMENSIONS: () = {
+        assert!(TOPICS > 0 && TOPICS <= NUM_TOPICS, "TOPICS must be in 1..=NUM_TOPICS");
+        assert!(WORDS == TOPICS.div_ceil(64), "WORDS must be TOPICS.div_ceil(64)");
+        assert!(SLOTS > 0 && SLOTS <= MAX_SLOTS_PER_TOPIC, "SLOTS must be in 1..=MAX_SLOTS_PER_TOPIC");
+        assert!(DATA > 0 && DATA <= MAX_DATA_SIZE, "DATA must be in 1..=MAX_DATA_SIZE");
+    };
+
+    /// `config` with its topic count, ring depths and payload sizes cut down to
+    /// this type's dimensions.
+    fn capped(config: &BusConfig) -> BusConfig {
+        let mut config = config.clone();
+        config.num_topics = config.num_topics.min(TOPICS);
+        config.channel_slots = config.channel_slots.map(|n| n.min(SLOTS as u32));
+        config.channel_data_size = config.channel_data_size.map(|n| n.min(DATA as u32));
+        config
+    }
+
+    fn open_inner(name: &str, config: &BusConfig, mode: OpenMode, group: usize) -> Result<Self, BusError> {
+        let () = Self::DIMENSIONS;
+        let config = &Self::capped(config);
+        let prefix = core::mem::size_of::<Journal<TOPICS, WORDS>>();
+        let created_layout = Layout::compute(
+            prefix,
+            config.num_topics,
+            &config.channel_data_size,
+            &config.channel_slots,
+            config.coalesce.words(),
+            config.payload_align,
+        );
+        let mem_size = created_layout.size;
+
+        let (mapping, created) = match mode {
+            OpenMode::ReadOnly => (SharedMapping::private(backing::map_readonly(name, &config.backing)?), false),
+            mode => backing::map_shared(name, &config.backing, mem_size, mode, config.fixed_addr)?,
         };
+        let read_only = matches!(mode, OpenMode::ReadOnly);
 
-        let ptr = shmem.as_ptr() as *mut Journal;
+        // The header has to be there before it can be read. The rest of the prefix is
+        // checked with the arena, once the header says the segment is this type's.
+        let need = core::mem::size_of::<JournalHeader>();
+        if mapping.len() < need {
+            return Err(BusError::SegmentTooSmall { got: mapping.len(), need });
+        }
+        let ptr = mapping.as_ptr() as *mut Journal<TOPICS, WORDS>;
 
         // Only on first creation, zero the region to ensure clean seq/pending bits.
         if created {
-            unsafe { core::ptr::write_bytes(ptr as *mut u8, 0, MEM_SIZE); }
+            unsafe {
+                core::ptr::write_bytes(ptr as *mut u8, 0, mem_size);
+                (*ptr).header.init(config, TOPICS, created_layout.slots);
+            }
+        }
+        if matches!(mode, OpenMode::Exclusive) {
+            unsafe { &(*ptr).header }.set_owner_pid(std::process::id());
         }
 
-        // POSIX named semaphore (works on macOS & Linux). Name MUST start with '/'.
-        let sem_name = CString::new(format!("/{}-notify", name)).unwrap();
-        let sem = unsafe { libc::sem_open(sem_name.as_ptr(), libc::O_CREAT, 0o666, 0) };
-        if sem == libc::SEM_FAILED {
-            panic!("sem_open failed: {}", std::io::Error::last_os_error());
+        // An attacher may race the creator's zeroing; wait for the header to be published.
+        let header = unsafe { &(*ptr).header };
+        let mut waited = 0;
+        while !header.is_ready() {
+            if waited >= 1000 {
+                return Err(BusError::NotInitialized);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+            waited += 1;
+        }
+        if !header.is_compatible() || !header.fits(TOPICS, SLOTS, DATA) {
+            return Err(BusError::IncompatibleLayout { layout_version: header.layout_version });
+        }
+        let pending_stride = if header.flags & FLAG_PADDED_PENDING != 0 { CACHE_LINE_WORDS } else { 1 };
+        // `fits` bounds `num_topics` by TOPICS, so this never exceeds the bitmap.
+        let pending_words = (header.num_topics as usize).div_ceil(64);
+        // Attachers take the slot sizes from the header, not from their own config.
+        let layout = Layout::compute(
+            prefix,
+            header.num_topics as usize,
+            &header.channel_data_size,
+            &header.channel_slots,
+            &header.coalesce,
+            header.payload_align,
+        );
+        if layout.slots != header.total_slots as usize {
+            return Err(BusError::IncompatibleLayout { layout_version: header.layout_version });
+        }
+        if mapping.len() < layout.size {
+            return Err(BusError::SegmentTooSmall { got: mapping.len(), need: layout.size });
+        }
+
+        let notifier: Arc<dyn Notifier> =
+            notify::open(name, config, group).map_err(|e| BusError::Notifier(e.to_string()))?.into();
+        if !read_only {
+            header.attach();
+            if !config.post_window.is_zero() {
+                header.widen_post_window(config.post_window);
+            }
         }
 
-        Self { shmem: Some(shmem), journal: ptr, sem }
+        Ok(Self {
+            _mapping: mapping,
+            read_only,
+            attached: !read_only,
+            journal: ptr,
+            pending_stride,
+            pending_words,
+            layout,
+            consumer: AtomicUsize::new(ack::NO_CONSUMER),
+            drain_cursor: AtomicUsize::new(0),
+            notifier,
+            group,
+            group_notifiers: Default::default(),
+            post_window: PostWindow::new(config.post_window),
+            name: name.to_owned(),
+            config: config.clone(),
+        })
+    }
+
+    /// Store a payload in the topic's ring. Refuses (and writes nothing) if the payload
+    /// doesn't fit the topic's `capacity`, so a caller never notifies a half-message.
+    #[inline]
+    pub fn write(&self, topic_id: usize, seq: u64, data: &[u8]) -> Result<(), BusError> {
+        self.write_at(topic_id, seq, data, self.now())
+    }
+
+    /// Like `write`, but stamps the message with a caller-supplied `publish_ts_nanos`.
+    #[inline]
+    pub fn write_at(&self, topic_id: usize, seq: u64, data: &[u8], publish_ts_nanos: u64) -> Result<(), BusError> {
+        self.write_typed_at(topic_id, seq, 0, 0, data, publish_ts_nanos)
+    }
+
+    /// Like `write`, tagging the message with `msg_type` so consumers of a topic that
+    /// carries several kinds of event can tell them apart without parsing the payload.
+    #[inline]
+    pub fn write_typed(&self, topic_id: usize, seq: u64, msg_type: u16, data: &[u8]) -> Result<(), BusError> {
+        self.write_typed_at(topic_id, seq, msg_type, 0, data, self.now())
+    }
+
+    /// Like `write_typed`, also stamping the payload's `schema_version`, so consumers
+    /// can branch on the encoding while old and new producers coexist.
+    #[inline]
+    pub fn write_versioned(
+        &self,
+        topic_id: usize,
+        seq: u64,
+        msg_type: u16,
+        schema_version: u8,
+        data: &[u8],
+    ) -> Result<(), BusError> {
+        self.write_typed_at(topic_id, seq, msg_type, schema_version, data, self.now())
+    }
+
+    #[inline]
+    fn write_typed_at(
+        &self,
+        topic_id: usize,
+        seq: u64,
+        msg_type: u16,
+        schema_version: u8,
+        data: &[u8],
+        publish_ts_nanos: u64,
+    ) -> Result<(), BusError> {
+        self.check_topic(topic_id);
+        check_seq(seq)?;
+        let max = self.capacity(topic_id);
+        if data.len() > max {
+            return Err(BusError::PayloadTooLarge { len: data.len(), max });
+        }
+        self.store(topic_id, Message { msg_type, schema_version, ..Message::with_payload(seq, data, publish_ts_nanos) });
+        Ok(())
+    }
+
+    /// Store an already-built message as is, flags included (replay, compression).
+    #[inline]
+    pub(crate) fn write_message(&self, topic_id: usize, msg: Message) -> Result<(), BusError> {
+        check_seq(msg.seq)?;
+        let max = self.capacity(topic_id);
+        if msg.data_len as usize > max {
+            return Err(BusError::PayloadTooLarge { len: msg.data_len as usize, max });
+        }
+        self.store(topic_id, msg);
+        Ok(())
+    }
+
+    /// Like `write`, but silently keeps only the first `capacity(topic_id)` bytes of an
+    /// oversized payload. Only `ReservedSeq` can fail it.
+    #[inline]
+    pub fn write_truncating(&self, topic_id: usize, seq: u64, data: &[u8]) -> Result<(), BusError> {
+        self.check_topic(topic_id);
+        check_seq(seq)?;
+        let len = data.len().min(self.capacity(topic_id));
+        self.store(topic_id, Message::with_payload(seq, &data[..len], self.now()));
+        Ok(())
+    }
+
+    /// Like `write`, but first checks whether the slot it is about to reuse holds a seq
+    /// some registered consumer hasn't acked. If so, the loss is counted in
+    /// `Stats::overflows` and reported to `on_overflow(topic_id, dropped_seq)` before the
+    /// store. With no consumer registered there is no watermark, so nothing counts as dropped.
+    pub fn write_with_overflow<F: FnOnce(usize, u64)>(
+        &self,
+        topic_id: usize,
+        seq: u64,
+        data: &[u8],
+        on_overflow: F,
+    ) -> Result<(), BusError> {
+        self.check_topic(topic_id);
+        check_seq(seq)?;
+        let topic = &self.layout.topics[topic_id];
+        if data.len() > topic.capacity {
+            return Err(BusError::PayloadTooLarge { len: data.len(), max: topic.capacity });
+        }
+        if let Some(resident) = self.unacked_resident(topic_id, topic, seq) {
+            Stats::bump(&self.stats_region().topics[topic_id].overflows);
+            on_overflow(topic_id, resident);
+        }
+        self.store_in(topic_id, topic, Message::with_payload(seq, data, self.now()));
+        Ok(())
+    }
+
+    /// Like `write`, but refuses with `BusError::WouldOverwriteUnacked` instead of
+    /// reusing a slot whose message a registered consumer hasn't acked, so the producer
+    /// can back off and retry. With every consumer acking, the ring becomes a lossless
+    /// bounded queue of `depth(topic_id)` messages. The check races only with acks,
+    /// which can only make room, so a refusal is at worst spurious; it assumes this is
+    /// the topic's only producer.
+    pub fn try_write(&self, topic_id: usize, seq: u64, data: &[u8]) -> Result<(), BusError> {
+        self.check_topic(topic_id);
+        check_seq(seq)?;
+        let topic = &self.layout.topics[topic_id];
+        if data.len() > topic.capacity {
+            return Err(BusError::PayloadTooLarge { len: data.len(), max: topic.capacity });
+        }
+        if let Some(unacked) = self.unacked_resident(topic_id, topic, seq) {
+            return Err(BusError::WouldOverwriteUnacked { topic_id, unacked });
+        }
+        self.store_in(topic_id, topic, Message::with_payload(seq, data, self.now()));
+        Ok(())
+    }
+
+    /// The seq in the slot `seq` would reuse, if some registered consumer hasn't acked it.
+    #[inline]
+    fn unacked_resident(&self, topic_id: usize, topic: &TopicLayout, seq: u64) -> Option<u64> {
+        let resident = unsafe { slot::resident_seq(self.slot_for(topic, seq)) };
+        (resident != EMPTY_SEQ && resident != seq && self.min_ack(topic_id).is_some_and(|ack| ack::ack_newer(resident, ack)))
+            .then_some(resident)
+    }
+
+    /// The clock stamping this handle's writes and heartbeats (`BusConfig::clock`).
+    #[inline]
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.config.clock
+    }
+
+    /// Time since `msg` was published, on this handle's clock (`BusConfig::clock`).
+    #[inline]
+    pub fn age(&self, msg: &Message) -> Duration {
+        Duration::from_nanos(self.now().saturating_sub(msg.publish_ts_nanos))
+    }
+
+    #[inline]
+    pub(crate) fn now(&self) -> u64 {
+        self.config.clock.now_nanos()
+    }
+
+    /// Payload bytes a slot of `topic_id` can hold, as configured for its channel
+    /// when the segment was created.
+    #[inline]
+    pub fn capacity(&self, topic_id: usize) -> usize {
+        self.layout.topics[topic_id].capacity
+    }
+
+    /// Bytes from one of `topic_id`'s slots to the next: the slot header plus its
+    /// capacity, rounded up to the payload alignment (`BusConfig::payload_align`).
+    #[inline]
+    pub fn stride(&self, topic_id: usize) -> usize {
+        self.layout.topics[topic_id].stride
+    }
+
+    /// Number of slots in `topic_id`'s ring: its channel's depth (`BusConfig::channel_slots`,
+    /// SLOTS_PER_TOPIC by default), or 1 if it is coalesced.
+    #[inline]
+    pub fn depth(&self, topic_id: usize) -> usize {
+        self.layout.topics[topic_id].depth
+    }
+
+    /// How many times slot `slot_index` of `topic_id`'s ring has been recycled: the
+    /// generation `seq / depth(topic_id)` of the seq it holds. A successful `read`
+    /// validates it implicitly (equal seqs, equal generations), but a reader expecting
+    /// seq `s` can compare against `s / depth` to see exactly how many laps it fell
+    /// behind. 0 for a slot that was never written, as for one holding a first-lap seq.
+    /// Panics if `slot_index` is outside the ring.
+    pub fn slot_generation(&self, topic_id: usize, slot_index: usize) -> u64 {
+        let topic = &self.layout.topics[topic_id];
+        assert!(slot_index < topic.depth, "slot {} out of range for a ring of {}", slot_index, topic.depth);
+        let seq = unsafe { slot::resident_seq(self.slot(topic, slot_index)) };
+        seq / topic.depth as u64
     }
 
+    /// The `i`th slot of a topic's ring.
     #[inline]
-    pub fn write(&self, topic_id: usize, seq: u64, data: &[u8]) {
-        unsafe {
-            let journal = &mut *self.journal;
-            let topic_offset = topic_id * SLOTS_PER_TOPIC;
-            let idx = topic_offset + (seq as usize % SLOTS_PER_TOPIC);
+    fn slot(&self, topic: &TopicLayout, i: usize) -> *mut SlotHeader {
+        debug_assert!(i < topic.depth);
+        unsafe { (self.journal as *mut u8).add(topic.offset + i * topic.stride) as *mut SlotHeader }
+    }
+
+    /// The slot `seq` maps to in a topic's ring.
+    #[inline]
+    fn slot_for(&self, topic: &TopicLayout, seq: u64) -> *mut SlotHeader {
+        self.slot(topic, seq as usize % topic.depth)
+    }
 
-            let mut msg = Message::default();
-            msg.seq = seq;
-            let len = data.len().min(DATA_SIZE);
-            msg.data[..len].copy_from_slice(&data[..len]);
-            msg.data_len = len as u32;
+    #[inline]
+    fn store(&self, topic_id: usize, msg: Message) {
+        self.store_in(topic_id, &self.layout.topics[topic_id], msg);
+    }
+
+    /// Seqlock-publish `msg` into its slot. The write paths reject `EMPTY_SEQ` with
+    /// `check_seq` before getting here.
+    #[inline]
+    fn store_in(&self, topic_id: usize, topic: &TopicLayout, msg: Message) {
+        debug_assert!(msg.seq != EMPTY_SEQ, "seq 0 is reserved as EMPTY_SEQ");
+        debug_assert!(msg.payload().len() <= topic.capacity);
+
+        let msg = self.sealed(topic_id, msg);
+        unsafe { slot::store(self.slot_for(topic, msg.seq), &msg, self.config.orderings) };
+        self.count_write(topic_id);
+    }
 
-            // Store the payload before publishing
-            journal.slots[idx] = msg;
+    /// Count a topic handed to a drain callback, in this handle's group's row.
+    #[inline]
+    fn count_drain(&self) {
+        Stats::bump(&self.stats_region().groups[self.group].drain_events);
+    }
+
+    /// Bookkeeping after a store to `topic_id`: active bit, write count, heartbeat.
+    #[inline]
+    pub(crate) fn count_write(&self, topic_id: usize) {
+        // A plain load first keeps the hot path free of a read-modify-write once set.
+        let active = unsafe { as_atomic(&(*self.journal).active_bits[topic_id / 64]) };
+        let bit = 1u64 << (topic_id % 64);
+        if active.load(Ordering::Relaxed) & bit == 0 {
+            active.fetch_or(bit, Ordering::Relaxed);
         }
+        Stats::bump(&self.stats_region().topics[topic_id].writes);
+        self.heartbeat(topic_id);
     }
 
+    /// Discard everything resident on `topic_id` and clear its pending bit, e.g. after
+    /// a feed resyncs. Each slot is cleared under its seqlock, so a concurrent reader
+    /// sees either the old message or nothing; afterwards the topic reads as never written.
+    pub fn clear_topic(&self, topic_id: usize) {
+        let topic = &self.layout.topics[topic_id];
+        for i in 0..topic.depth {
+            unsafe { slot::clear(self.slot(topic, i), self.config.orderings) };
+        }
+        self.pending_word(topic_id / 64).fetch_and(!(1u64 << (topic_id % 64)), Ordering::AcqRel);
+        unsafe { as_atomic(&(*self.journal).active_bits[topic_id / 64]) }
+            .fetch_and(!(1u64 << (topic_id % 64)), Ordering::Relaxed);
+    }
+
+    /// Producer: mark `topic_id` as alive without publishing anything, so a quiet
+    /// market isn't mistaken for a dead feed. `write` does this implicitly.
+    #[inline]
+    pub fn heartbeat(&self, topic_id: usize) {
+        let beat = unsafe { as_atomic(&self.stats_region().topics[topic_id].last_heartbeat_ns) };
+        beat.store(self.now(), Ordering::Release);
+    }
+
+    /// Consumer: true if `topic_id` has had no write or heartbeat for longer than
+    /// `max_age` (or ever). Compares against this handle's clock (`BusConfig::clock`).
+    #[inline]
+    pub fn is_stale(&self, topic_id: usize, max_age: Duration) -> bool {
+        let beat = unsafe { as_atomic(&self.stats_region().topics[topic_id].last_heartbeat_ns) };
+        match beat.load(Ordering::Acquire) {
+            0 => true,
+            last => self.now().saturating_sub(last) > max_age.as_nanos() as u64,
+        }
+    }
+
+    /// The message with `seq`, if it is still resident. Every store of a seq goes to
+    /// slot `seq % depth(topic_id)`, so no other slot can ever hold it: a miss means
+    /// the seq was overwritten, isn't published yet, or its slot stayed mid-write for
+    /// all `SEQLOCK_RETRIES`. `read_status` and `read_bounded` tell those apart;
+    /// scanning the rest of the ring can't turn up the message.
     #[inline]
     pub fn read(&self, topic_id: usize, seq: u64) -> Option<Message> {
-        unsafe {
-            let journal = &*self.journal;
-            let topic_offset = topic_id * SLOTS_PER_TOPIC;
-            let idx = topic_offset + (seq as usize % SLOTS_PER_TOPIC);
-            let msg = journal.slots[idx];
-            if msg.seq == seq { Some(msg) } else { None }
+        self.check_topic(topic_id);
+        self.read_in(&self.layout.topics[topic_id], seq)
+    }
+
+    /// Like `read`, but into the caller's `out`, copying only the payload bytes, so a
+    /// loop reusing one buffer never zeroes or moves a whole `MAX_DATA_SIZE` array.
+    /// Returns false on a miss, leaving `out` unspecified.
+    #[inline]
+    pub fn read_into(&self, topic_id: usize, seq: u64, out: &mut Message) -> bool {
+        self.check_topic(topic_id);
+        let topic = &self.layout.topics[topic_id];
+        if seq == EMPTY_SEQ {
+            return false;
+        }
+        let loaded =
+            unsafe { slot::load_into(self.slot_for(topic, seq), topic.capacity, SEQLOCK_RETRIES, self.config.orderings, out) };
+        let found = matches!(loaded, Ok((true, _))) && out.seq == seq;
+        if found {
+            self.open_in_place(topic_id, out);
         }
+        found
     }
 
+    /// Undo `sealed` on a message just read (see `open_in_place`).
     #[inline]
-    pub fn get_latest_seq(&self, topic_id: usize) -> u64 {
-        unsafe {
-            let journal = &*self.journal;
-            let topic_offset = topic_id * SLOTS_PER_TOPIC;
-            let mut latest = 0u64;
-            for i in 0..SLOTS_PER_TOPIC {
-                let msg = journal.slots[topic_offset + i];
-                if msg.seq > latest { latest = msg.seq; }
+    fn opened(&self, topic_id: usize, mut msg: Message) -> Message {
+        self.open_in_place(topic_id, &mut msg);
+        msg
+    }
+
+    #[inline]
+    fn read_in(&self, topic: &TopicLayout, seq: u64) -> Option<Message> {
+        if seq == EMPTY_SEQ {
+            return None;
+        }
+        unsafe { slot::load(self.slot_for(topic, seq), topic.capacity, self.config.orderings) }
+            .filter(|msg| msg.seq == seq)
+            .map(|msg| self.opened(topic.id, msg))
+    }
+
+    /// Like `read`, but tries the seqlock at most `attempts` times (`read` uses
+    /// `SEQLOCK_RETRIES`) and says whether a miss means "no such message" or "couldn't
+    /// get a stable read", so a caller can use a small budget on hot topics and notice
+    /// when one is pathologically contended.
+    pub fn read_bounded(&self, topic_id: usize, seq: u64, attempts: usize) -> ReadOutcome {
+        self.check_topic(topic_id);
+        let topic = &self.layout.topics[topic_id];
+        match unsafe { slot::load_within(self.slot_for(topic, seq), topic.capacity, attempts, self.config.orderings) } {
+            Ok((Some(msg), retries)) if msg.seq == seq && seq != EMPTY_SEQ => {
+                ReadOutcome::Read { msg: self.opened(topic_id, msg), retries }
             }
-            latest
+            Ok(_) => ReadOutcome::Missing,
+            Err(slot::Contended) => ReadOutcome::Contended,
+        }
+    }
+
+    /// Borrow the message at `seq` in place instead of copying the whole slot.
+    /// See `MessageRef` for the re-check the caller must do after reading the bytes.
+    #[inline]
+    pub fn read_ref(&self, topic_id: usize, seq: u64) -> Option<MessageRef<'_>> {
+        self.check_topic(topic_id);
+        if seq == EMPTY_SEQ {
+            return None;
+        }
+        let topic = &self.layout.topics[topic_id];
+        unsafe { slot::load_ref(self.slot_for(topic, seq), topic.capacity, seq, self.config.orderings) }
+    }
+
+    /// Like `read`, but tells a missed message apart from one not published yet, so a
+    /// catch-up loop knows whether to skip ahead or wait. A slot caught mid-write for
+    /// the whole retry budget reports `Future`.
+    #[inline]
+    pub fn read_status(&self, topic_id: usize, seq: u64) -> SlotStatus {
+        self.check_topic(topic_id);
+        let topic = &self.layout.topics[topic_id];
+        match unsafe { slot::load(self.slot_for(topic, seq), topic.capacity, self.config.orderings) } {
+            Some(msg) if msg.seq == seq && seq != EMPTY_SEQ => SlotStatus::Present(self.opened(topic_id, msg)),
+            Some(msg) if seq_newer(msg.seq, seq) => SlotStatus::Overwritten { current_seq: msg.seq },
+            _ => SlotStatus::Future,
+        }
+    }
+
+    /// Like `read`, but verifies the payload CRC so corruption surfaces as an error
+    /// instead of garbage handed to a parser.
+    #[inline]
+    pub fn read_checked(&self, topic_id: usize, seq: u64) -> Result<Option<Message>, IntegrityError> {
+        match self.read(topic_id, seq) {
+            Some(msg) if !msg.crc_ok() => Err(IntegrityError {
+                topic_id,
+                seq,
+                stored: msg.crc,
+                computed: crc32(msg.payload()),
+            }),
+            other => Ok(other),
         }
     }
 
-    /// Producer: mark topic as pending and (maybe) post the semaphore.
+    /// Newest resident message for the topic, or None if nothing was ever written to it.
+    #[inline]
+    pub fn read_latest(&self, topic_id: usize) -> Option<Message> {
+        self.check_topic(topic_id);
+        self.latest_in(&self.layout.topics[topic_id])
+    }
+
+    #[inline]
+    fn latest_in(&self, topic: &TopicLayout) -> Option<Message> {
+        self.best_resident(topic, seq_newer)
+    }
+
+    /// Oldest resident message for the topic (by `seq_newer`), or None if nothing was
+    /// ever written to it. With `read_latest` this bounds what is still replayable:
+    /// every seq in `[oldest, latest]` that hasn't been overwritten can be read.
+    #[inline]
+    pub fn read_oldest(&self, topic_id: usize) -> Option<Message> {
+        self.check_topic(topic_id);
+        self.oldest_in(&self.layout.topics[topic_id])
+    }
+
+    #[inline]
+    fn oldest_in(&self, topic: &TopicLayout) -> Option<Message> {
+        self.best_resident(topic, |a, b| seq_newer(b, a))
+    }
+
+    /// The resident message whose seq `better(seq, best)` prefers to all the others.
+    /// Slots load into two buffers in turn, so the ring costs one copy per payload.
+    fn best_resident(&self, topic: &TopicLayout, better: impl Fn(u64, u64) -> bool) -> Option<Message> {
+        let mut bufs = [Message::default(), Message::default()];
+        let mut best: Option<usize> = None;
+        for i in 0..topic.depth {
+            let scratch = best.map_or(0, |b| 1 - b);
+            let loaded = unsafe {
+                slot::load_into(self.slot(topic, i), topic.capacity, SEQLOCK_RETRIES, self.config.orderings, &mut bufs[scratch])
+            };
+            let seq = bufs[scratch].seq;
+            if matches!(loaded, Ok((true, _))) && seq != EMPTY_SEQ && best.is_none_or(|b| better(seq, bufs[b].seq)) {
+                best = Some(scratch);
+            }
+        }
+        let [first, second] = bufs;
+        best.map(|b| self.opened(topic.id, if b == 0 { first } else { second }))
+    }
+
+    /// Latest message of every topic in `mask` that has data, in topic order: the
+    /// current state to bootstrap from before switching to the drain loop. Each entry
+    /// is a seqlock-consistent read, but the topics are read one after another, so
+    /// the set as a whole isn't a single point in time.
+    pub fn snapshot(&self, mask: &SubscriptionMask) -> Vec<(usize, Message)> {
+        mask.into_iter()
+            .take_while(|&t| t < self.num_topics())
+            .filter_map(|t| self.latest_in(&self.layout.topics[t]).map(|msg| (t, msg)))
+            .collect()
+    }
+
+    /// Every resident message of every topic in `mask`, whole rings rather than just
+    /// the latest, ordered by `publish_ts_nanos` (then topic and seq): the recent
+    /// history in approximate publish order, for a one-shot debug dump. Much heavier
+    /// than a drain, and despite the name it leaves the pending bits alone. Timestamps
+    /// from producers on different clocks (`BusConfig::clock`, `write_at`) don't
+    /// interleave meaningfully.
+    pub fn drain_all_resident(&self, mask: &SubscriptionMask) -> Vec<(usize, Message)> {
+        let mut all = Vec::new();
+        for t in mask.into_iter().take_while(|&t| t < self.num_topics()) {
+            self.for_each_resident(&self.layout.topics[t], |msg| all.push((t, msg)));
+        }
+        all.sort_by_key(|(t, msg)| (msg.publish_ts_nanos, *t, msg.seq));
+        all
+    }
+
+    /// Every written, consistently read slot of a topic, in ring order (not seq order).
+    #[inline]
+    fn for_each_resident<F: FnMut(Message)>(&self, topic: &TopicLayout, mut on_msg: F) {
+        for i in 0..topic.depth {
+            if let Some(msg) = unsafe { slot::load(self.slot(topic, i), topic.capacity, self.config.orderings) }
+                && msg.seq != EMPTY_SEQ
+            {
+                on_msg(self.opened(topic.id, msg));
+            }
+        }
+    }
+
+    /// Newest resident seq for the topic (by `seq_newer`), or `EMPTY_SEQ` if it has no data.
+    #[inline]
+    pub fn get_latest_seq(&self, topic_id: usize) -> u64 {
+        self.read_latest(topic_id).map_or(EMPTY_SEQ, |msg| msg.seq)
+    }
+
+    /// The segment's layout description.
+    #[inline]
+    pub fn header(&self) -> &JournalHeader {
+        unsafe { &(*self.journal).header }
+    }
+
+    #[inline]
+    fn stats_region(&self) -> &Stats<TOPICS> {
+        unsafe { &(*self.journal).stats }
+    }
+
+    /// Snapshot of the bus-wide counters. Cheap enough to poll from a monitor process.
+    pub fn stats(&self) -> StatsSnapshot {
+        self.stats_region().snapshot()
+    }
+
+    /// `stats()` rendered for a Prometheus scrape (see `StatsSnapshot::to_prometheus`).
+    /// Only loads the counters, so it doesn't disturb producers or consumers.
+    pub fn stats_prometheus(&self) -> String {
+        self.stats().to_prometheus()
+    }
+
+    /// `stats()` summed over each exchange's topics (see `StatsSnapshot::by_exchange`).
+    pub fn stats_by_exchange(&self) -> [ExchangeStats; NUM_EXCHANGES] {
+        self.stats().by_exchange()
+    }
+
+    /// Topics the segment holds (`BusConfig::num_topics` of its creator); valid ids
+    /// are `0..num_topics()`.
+    #[inline]
+    pub fn num_topics(&self) -> usize {
+        self.layout.topics.len()
+    }
+
+    /// Panic unless `topic_id` names one of the segment's topics. The entry points that
+    /// take a caller's id check it in release builds too: past the last topic lie other
+    /// parts of the segment, and a wrong id must not reach the pointer arithmetic.
+    #[inline]
+    #[track_caller]
+    fn check_topic(&self, topic_id: usize) {
+        let n = self.num_topics();
+        assert!(topic_id < n, "topic_id {} out of range (the bus has {} topics)", topic_id, n);
+    }
+
+    /// Word `w` of this handle's group's pending bitmap, viewed as an AtomicU64.
+    #[inline]
+    fn pending_word(&self, w: usize) -> &AtomicU64 {
+        self.group_pending_word(self.group, w)
+    }
+
+    /// Producer: mark topic as pending and (maybe) post the semaphore, for the default
+    /// group and every consumer group subscribed to the topic.
     #[inline]
     pub fn notify(&self, topic_id: usize) {
-        debug_assert!(topic_id < NUM_TOPICS);
+        self.check_topic(topic_id);
         let word_idx = topic_id / 64;
         let bit = 1u64 << (topic_id % 64);
+        self.post_held(false);
+
+        let stats = self.stats_region();
+        Stats::bump(&stats.topics[topic_id].notifies);
 
-        unsafe {
-            // Interpret the pending_bits word as AtomicU64
-            let journal = &*self.journal;
-            let word_ptr = journal.pending_bits.as_ptr().add(word_idx) as *const AtomicU64;
-            let prev = (*word_ptr).fetch_or(bit, Ordering::Release);
+        // Only post when transitioning 0 -> 1 for this bit (prevents semaphore overshoot).
+        if !self.mark_in_group(0, word_idx, bit) {
+            Stats::bump(&stats.topics[topic_id].posts_suppressed);
+        }
+        self.notify_groups(word_idx, bit);
+    }
+
+    /// Full `SeqCst` fence: every write and notify issued by this thread is ordered
+    /// before anything it does afterwards, including signals outside the bus.
+    ///
+    /// Not needed between `write` and `notify` (or for consumers that drain), since
+    /// the slot's Release publish and the pending bit already order those. Use it when
+    /// something other than the bus, e.g. an ack to an upstream feed or a flag in
+    /// another segment, must not become visible before the messages it vouches for.
+    #[inline]
+    pub fn fence(&self) {
+        fence(Ordering::SeqCst);
+    }
 
-            // Only post when transitioning 0 -> 1 for this bit (prevents semaphore overshoot).
-            if (prev & bit) == 0 {
-                libc::sem_post(self.sem);
+    /// Producer: write several `(topic_id, seq, data)` items, then mark them all pending
+    /// with one `fetch_or` per touched word and at most one semaphore post, so the
+    /// consumer wakes once and sees the whole batch.
+    ///
+    /// If any payload is too large, or any seq is `EMPTY_SEQ`, nothing is written or notified.
+    pub fn write_batch(&self, items: &[(usize, u64, &[u8])]) -> Result<(), BusError> {
+        items.iter().for_each(|&(topic_id, _, _)| self.check_topic(topic_id));
+        if let Some(&(topic_id, _, data)) = items.iter().find(|&&(t, _, data)| data.len() > self.capacity(t)) {
+            return Err(BusError::PayloadTooLarge { len: data.len(), max: self.capacity(topic_id) });
+        }
+        items.iter().try_for_each(|&(_, seq, _)| check_seq(seq))?;
+
+        let stats = self.stats_region();
+        let mut bits = [0u64; WORDS];
+        for &(topic_id, seq, data) in items {
+            self.store(topic_id, Message::with_payload(seq, data, self.now()));
+            bits[topic_id / 64] |= 1u64 << (topic_id % 64);
+            Stats::bump(&stats.topics[topic_id].notifies);
+        }
+
+        let mut transitioned = false;
+        for (w, &word) in bits[..self.pending_words].iter().enumerate() {
+            if word != 0 {
+                let prev = self.group_pending_word(0, w).fetch_or(word, self.config.orderings.release());
+                transitioned |= (word & !prev) != 0;
             }
         }
+        self.post_held(false);
+        if transitioned {
+            self.post_group(0);
+        } else if let Some(&(first, _, _)) = items.first() {
+            Stats::bump(&stats.topics[first].posts_suppressed);
+        }
+        // Groups get a post per word that newly went pending rather than one overall.
+        for (w, &word) in bits[..self.pending_words].iter().enumerate() {
+            if word != 0 {
+                self.notify_groups(w, word);
+            }
+        }
+        Ok(())
     }
 
-    /// Consumer: block until any topic is pending.
+    /// Ask every consumer to stop: sets the header's shutdown flag, then posts once per
+    /// attached handle so each blocked waiter wakes. Consumers check `is_shutting_down`
+    /// after `wait` returns; a drain already in progress is not interrupted.
+    pub fn shutdown(&self) {
+        self.header().set_shutting_down();
+        let times = self.header().attached().max(1);
+        if let Some(notifier) = self.group_notifier(0) {
+            for _ in 0..times {
+                notifier.post();
+            }
+        }
+        self.post_groups(times);
+    }
+
+    /// True once any process has called `shutdown` on this bus.
+    #[inline]
+    pub fn is_shutting_down(&self) -> bool {
+        self.header().is_shutting_down()
+    }
+
+    /// After a wakeup: if the bus is shutting down, pass the token on so the next
+    /// waiter wakes too (whoever consumed the shutdown posts may not be the waiters).
+    #[inline]
+    fn relay_shutdown(&self) -> bool {
+        let shutting_down = self.is_shutting_down();
+        if shutting_down {
+            self.notifier.post();
+        }
+        shutting_down
+    }
+
+    /// Consumer: block until any topic is pending, or the bus is shutting down.
+    ///
+    /// Once a handle has set a `BusConfig::post_window` on the segment, a producer
+    /// that goes quiet may still be holding the post for bits it set, so the wait
+    /// wakes every window to look at the pending bits itself.
     #[inline]
     pub fn wait(&self) {
-        unsafe {
-            // Will block until someone sem_post()s.
-            libc::sem_wait(self.sem);
+        if self.is_shutting_down() {
+            return;
+        }
+        match self.header().post_window() {
+            None => self.notifier.wait(),
+            Some(window) => while !self.wait_held(window) {},
+        }
+        self.relay_shutdown();
+    }
+
+    /// Consumer: block until a topic is pending or `timeout` elapses.
+    /// Returns true if it was woken by a notification (or the bus is shutting down).
+    /// Under a post window it also returns true, within a window, for bits whose post
+    /// is held, as `wait` does.
+    #[inline]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        if self.is_shutting_down() {
+            return true;
+        }
+        let woken = match self.header().post_window() {
+            None => self.notifier.wait_timeout(timeout),
+            Some(window) => {
+                let deadline = std::time::Instant::now() + timeout;
+                loop {
+                    let left = deadline.saturating_duration_since(std::time::Instant::now());
+                    if self.wait_held(window.min(left)) {
+                        break true;
+                    }
+                    if left <= window {
+                        break false;
+                    }
+                }
+            }
+        };
+        if woken {
+            self.relay_shutdown();
+        }
+        woken
+    }
+
+    /// Wait up to `window` for a post; true if one came, or bits are pending (their
+    /// post may be held) or the bus is shutting down.
+    fn wait_held(&self, window: Duration) -> bool {
+        self.notifier.wait_timeout(window) || self.any_pending() || self.is_shutting_down()
+    }
+
+    /// Consumer: spin up to `spin_iters` times watching the pending bitmap before
+    /// parking in `sem_wait`. Saves the syscall when the next notify is imminent.
+    pub fn wait_spin(&self, spin_iters: u32) {
+        for _ in 0..spin_iters {
+            if self.any_pending() {
+                // Consume the matching token (if it's there) so the semaphore stays in step.
+                self.try_wait();
+                return;
+            }
+            core::hint::spin_loop();
         }
+        self.wait();
+    }
+
+    /// Re-arm the semaphore if topics are pending but no token is left to wake anyone,
+    /// which happens when a consumer dies between taking the token and clearing the
+    /// bits (`notify` won't post again for a bit that is already set). Returns true if
+    /// it had to post. Call it on consumer startup and whenever a consumer seems stalled.
+    pub fn resync(&self) -> bool {
+        if !self.any_pending() {
+            return false;
+        }
+        // Take a token if there is one, then post, so there is now at least one.
+        let had_token = self.notifier.try_wait();
+        self.notifier.post();
+        !had_token
     }
 
     /// Consumer: non-blocking try-wait; returns true if it consumed a token.
     #[inline]
     pub fn try_wait(&self) -> bool {
-        let rc = unsafe { libc::sem_trywait(self.sem) };
-        rc == 0
-    }
-
-    #[inline]
-    pub fn drain_pending_mask<F: FnMut(usize)>(&self, mask: &[u64; BITWORDS], mut on_topic: F) {
-        unsafe {
-            let journal = &*self.journal;
-            for w in 0..BITWORDS {
-                // take pending
-                let word_atomic = &*(journal.pending_bits.as_ptr().add(w) as *const AtomicU64);
-                let mut bits = word_atomic.swap(0, Ordering::Acquire);
-                // keep only subscribed bits
-                bits &= mask[w];
-
-                while bits != 0 {
-                    let tz = bits.trailing_zeros() as usize;
-                    let topic_id = w * 64 + tz;
-                    on_topic(topic_id);
-                    bits &= bits - 1;
-                }
+        self.notifier.try_wait()
+    }
+
+    /// Unconsumed wakeup tokens on this handle's group semaphore, or None where the
+    /// backend can't say (Windows Events, macOS semaphores). A value that stays high
+    /// while `pending_count` does too means the consumer has stalled rather than
+    /// fallen briefly behind.
+    pub fn sem_value(&self) -> Option<i32> {
+        self.notifier.value()
+    }
+
+    /// Fd that polls readable while a wakeup is waiting, for registering the bus with
+    /// epoll. Only the `NotifyBackend::Fifo` backend has one (a named semaphore can't
+    /// be polled). On readiness call `drain_now`, which also consumes the wakeups.
+    #[cfg(target_os = "linux")]
+    pub fn notify_fd(&self) -> Option<std::os::fd::RawFd> {
+        self.notifier.fd()
+    }
+
+    #[inline]
+    pub fn drain_pending_mask<F: FnMut(usize)>(&self, mask: &SubscriptionMask, mut on_topic: F) {
+        for w in 0..self.pending_words {
+            // take pending
+            let mut bits = self.pending_word(w).swap(0, self.config.orderings.acquire());
+            // keep only subscribed bits
+            bits &= mask.word(w);
+
+            while bits != 0 {
+                let tz = bits.trailing_zeros() as usize;
+                let topic_id = w * 64 + tz;
+                self.count_drain();
+                on_topic(topic_id);
+                bits &= bits - 1;
+            }
+        }
+    }
+
+    /// `drain_pending_mask`, returning each drained topic with its latest message
+    /// instead of calling back, for a consumer that sorts, dedupes or throttles the
+    /// batch as a whole. Allocates; keep the callback for hot paths. A drained topic
+    /// with no readable latest message (mid-write for too long) is left out.
+    pub fn drain_collect(&self, mask: &SubscriptionMask) -> Vec<(usize, Message)> {
+        let mut batch = Vec::new();
+        self.drain_pending_mask(mask, |t| {
+            if let Some(msg) = self.read_latest(t) {
+                batch.push((t, msg));
+            }
+        });
+        batch
+    }
+
+    /// Like `drain_pending_mask`, but each pass starts just after the last topic the
+    /// previous pass delivered and wraps around, so when the callback can't keep up the
+    /// high topic ids aren't always served last. The cursor is per `Bus` handle.
+    pub fn drain_round_robin<F: FnMut(usize)>(&self, mask: &SubscriptionMask, mut on_topic: F) {
+        let words = self.pending_words;
+        let mut taken = [0u64; WORDS];
+        for (w, taken) in taken[..words].iter_mut().enumerate() {
+            *taken = self.pending_word(w).swap(0, self.config.orderings.acquire()) & mask.word(w);
+        }
+        let start = self.drain_cursor.load(Ordering::Relaxed) % self.num_topics();
+        let (first_word, first_bit) = (start / 64, start % 64);
+
+        // The starting word is visited twice: its bits from the cursor up first, and
+        // the ones below the cursor last, after wrapping around.
+        for i in 0..=words {
+            let w = (first_word + i) % words;
+            let mut bits = match i {
+                0 => taken[w] & (!0u64 << first_bit),
+                _ if i == words => taken[w] & ((1u64 << first_bit) - 1),
+                _ => taken[w],
+            };
+            while bits != 0 {
+                let topic_id = w * 64 + bits.trailing_zeros() as usize;
+                self.count_drain();
+                self.drain_cursor.store(topic_id + 1, Ordering::Relaxed);
+                on_topic(topic_id);
+                bits &= bits - 1;
+            }
+        }
+    }
+
+    /// True if any topic is pending. Doesn't consume a token or clear bits, so it's
+    /// a cheap check for whether a drain pass is worth doing.
+    #[inline]
+    pub fn any_pending(&self) -> bool {
+        (0..self.pending_words).any(|w| self.pending_word(w).load(Ordering::Acquire) != 0)
+    }
+
+    /// Number of pending topics, without clearing them.
+    #[inline]
+    pub fn pending_count(&self) -> u32 {
+        (0..self.pending_words).map(|w| self.pending_word(w).load(Ordering::Acquire).count_ones()).sum()
+    }
+
+    /// Topics currently pending and in `mask`, without clearing them (unlike draining).
+    /// The words are loaded once up front, so the iterator is a snapshot.
+    pub fn pending_topics(&self, mask: &SubscriptionMask) -> Topics {
+        let mut words = [0u64; WORDS];
+        let len = self.pending_snapshot_into(&mut words);
+        for (w, word) in words[..len].iter_mut().enumerate() {
+            *word &= mask.word(w);
+        }
+        SubscriptionMask::from_words(&words[..len]).into_iter()
+    }
+
+    /// Topics that have received data since the segment was created (or their last
+    /// `clear_topic`). Unlike pending bits, draining doesn't clear these, so a monitor
+    /// can list live topics without scanning every slot.
+    pub fn active_topics(&self) -> Topics {
+        let words: [u64; WORDS] = core::array::from_fn(|w| {
+            unsafe { as_atomic(&(*self.journal).active_bits[w]) }.load(Ordering::Relaxed)
+        });
+        SubscriptionMask::from_words(&words[..self.pending_words]).into_iter()
+    }
+
+    /// The raw pending bitmap (bit `t % 64` of word `t / 64` is topic `t`), loaded
+    /// without clearing anything: one word per 64 of the segment's topics. For
+    /// dashboards; decode ids with `topic_coords`.
+    pub fn pending_snapshot(&self) -> Vec<u64> {
+        (0..self.pending_words).map(|w| self.pending_word(w).load(Ordering::Acquire)).collect()
+    }
+
+    /// `pending_snapshot` into `out` without allocating, e.g. a `[u64; BITWORDS]`
+    /// reused across polls. Fills as many words as both hold and returns that count.
+    pub fn pending_snapshot_into(&self, out: &mut [u64]) -> usize {
+        let len = out.len().min(self.pending_words);
+        for (w, word) in out[..len].iter_mut().enumerate() {
+            *word = self.pending_word(w).load(Ordering::Acquire);
+        }
+        len
+    }
+
+    /// Clear the pending bits in `mask` without handing them to anyone, e.g. for topics
+    /// a consumer just unsubscribed from. Returns how many were pending.
+    pub fn discard_pending(&self, mask: &SubscriptionMask) -> u32 {
+        mask.words()
+            .iter()
+            .take(self.pending_words)
+            .enumerate()
+            .filter(|&(_, &bits)| bits != 0)
+            .map(|(w, &bits)| (self.pending_word(w).fetch_and(!bits, Ordering::Acquire) & bits).count_ones())
+            .sum()
+    }
+
+    /// Take and deliver only the pending bits in `mask`, leaving every other topic pending.
+    fn drain_only<F: FnMut(usize)>(&self, mask: &SubscriptionMask, on_topic: &mut F) {
+        for (w, &subscribed) in mask.words().iter().take(self.pending_words).enumerate() {
+            if subscribed == 0 {
+                continue;
+            }
+            let mut bits = self.pending_word(w).fetch_and(!subscribed, self.config.orderings.acquire()) & subscribed;
+            while bits != 0 {
+                let topic_id = w * 64 + bits.trailing_zeros() as usize;
+                self.count_drain();
+                on_topic(topic_id);
+                bits &= bits - 1;
+            }
+        }
+    }
+
+    /// Drain the schedule's groups highest priority first. Each group is drained
+    /// fully, and after every group the higher ones are re-checked so new critical
+    /// work preempts the rest of the pass. Topics outside every group stay pending.
+    pub fn drain_prioritized<F: FnMut(usize)>(&self, schedule: &DrainSchedule, mut on_topic: F) {
+        let mut i = 0;
+        while i < schedule.len() {
+            self.drain_only(schedule.mask(i), &mut on_topic);
+            if (0..i).any(|h| self.pending_topics(schedule.mask(h)).next().is_some()) {
+                i = 0;
+            } else {
+                i += 1;
             }
         }
     }
 
-    pub fn wait_and_drain_mask<F: FnMut(usize)>(&self, mask: &[u64; BITWORDS], mut on_topic: F) {
+    /// Like `drain_pending_mask`, but reads each pending topic's latest message once and
+    /// hands it to `on_msg` along with the topic_id. Topics with no resident message are skipped.
+    #[inline]
+    pub fn drain_pending_with<F: FnMut(usize, Message)>(&self, mask: &SubscriptionMask, mut on_msg: F) {
+        self.drain_pending_mask(mask, |t| {
+            if let Some(msg) = self.read_latest(t) {
+                on_msg(t, msg);
+            }
+        });
+    }
+
+    pub fn wait_and_drain_mask<F: FnMut(usize)>(&self, mask: &SubscriptionMask, on_topic: F) {
+        self.wait();
+        self.drain_now(mask, on_topic);
+    }
+
+    /// Like `wait_and_drain_mask`, but gives up after `timeout` so the caller's loop
+    /// can do periodic work (flush metrics, check `is_shutting_down`, `drain_now`).
+    pub fn wait_and_drain_mask_timeout<F: FnMut(usize)>(
+        &self,
+        mask: &SubscriptionMask,
+        timeout: Duration,
+        mut on_topic: F,
+    ) -> DrainOutcome {
+        if !self.wait_timeout(timeout) {
+            return DrainOutcome::TimedOut;
+        }
+        let mut drained = 0;
+        self.drain_now(mask, |t| {
+            drained += 1;
+            on_topic(t);
+        });
+        DrainOutcome::Drained(drained)
+    }
+
+    /// Wait up to `timeout` for exactly `seq` to be resident on `topic_id` and return
+    /// it, e.g. the reply to a request published with a known seq. A newer message
+    /// doesn't count; None if the deadline passes, the bus shuts down, or the seq was
+    /// already overwritten (it can't appear any more).
+    ///
+    /// Sleeps on this handle's semaphore and takes `topic_id`'s bit in this handle's
+    /// group each time round, so the next notify of the topic posts again. Other
+    /// groups' bits are never touched, and the bit and any wakeups taken are handed
+    /// back before returning, but while it waits a drain loop sharing the handle's
+    /// group may be woken late. On a group handle `topic_id` must be in the group's
+    /// mask, or only the deadline ends the wait.
+    pub fn read_blocking(&self, topic_id: usize, seq: u64, timeout: Duration) -> Option<Message> {
+        let deadline = std::time::Instant::now() + timeout;
+        let topic = SubscriptionMask::from_topics(&[topic_id]);
+        let mut taken = false;
+        let found = loop {
+            // Take the bit before looking, so a write landing after the look still posts.
+            taken |= self.discard_pending(&topic) != 0;
+            match self.read_status(topic_id, seq) {
+                SlotStatus::Present(msg) => break Some(msg),
+                SlotStatus::Overwritten { .. } => break None,
+                SlotStatus::Future => {}
+            }
+            let left = deadline.saturating_duration_since(std::time::Instant::now());
+            if left.is_zero() || self.is_shutting_down() {
+                break None;
+            }
+            self.wait_timeout(left);
+        };
+        if taken {
+            let (w, bit) = (topic_id / 64, 1u64 << (topic_id % 64));
+            self.pending_word(w).fetch_or(bit, self.config.orderings.release());
+        }
+        self.resync();
+        found
+    }
+
+    /// Like `wait_and_drain_mask`, but hands out at most `max_topics` topics and leaves
+    /// the rest pending, so one burst can't keep the consumer thread for an unbounded
+    /// time. If it stops with subscribed topics still pending it re-posts the semaphore
+    /// once, so the next call returns straight away. Topics are taken round robin from
+    /// where the previous budgeted pass stopped (the `drain_round_robin` cursor), so a
+    /// busy low topic can't starve the high ones. Returns how many it delivered.
+    pub fn wait_and_drain_mask_budget<F: FnMut(usize)>(
+        &self,
+        mask: &SubscriptionMask,
+        max_topics: usize,
+        mut on_topic: F,
+    ) -> usize {
         self.wait();
+        let mut drained = self.drain_budget(mask, max_topics, &mut on_topic);
+        while drained < max_topics && self.try_wait() {
+            drained += self.drain_budget(mask, max_topics - drained, &mut on_topic);
+        }
+        if drained == max_topics && self.pending_topics(mask).next().is_some() {
+            self.notifier.post();
+        }
+        drained
+    }
+
+    /// Like `drain_now`, but bounded by wall-clock time rather than topic count: after
+    /// the first pass it only goes round again (for topics that went pending during
+    /// the previous one) while `deadline` hasn't passed. A pass is at most one visit per
+    /// topic, so the overrun is bounded too. If it stops with subscribed topics still
+    /// pending it re-posts the semaphore once, as `wait_and_drain_mask_budget` does.
+    /// Returns how many topics it delivered.
+    pub fn drain_until<F: FnMut(usize)>(&self, mask: &SubscriptionMask, deadline: std::time::Instant, mut on_topic: F) -> usize {
+        let mut drained = 0;
+        let mut deliver = |t| {
+            drained += 1;
+            on_topic(t);
+        };
+        self.drain_pending_mask(mask, &mut deliver);
+        while std::time::Instant::now() < deadline {
+            if !self.try_wait() {
+                return drained;
+            }
+            self.drain_pending_mask(mask, &mut deliver);
+        }
+        if self.pending_topics(mask).next().is_some() {
+            self.notifier.post();
+        }
+        drained
+    }
+
+    /// One round-robin pass taking at most `budget` subscribed topics; the rest keep
+    /// their pending bits.
+    fn drain_budget<F: FnMut(usize)>(&self, mask: &SubscriptionMask, budget: usize, on_topic: &mut F) -> usize {
+        let words = self.pending_words;
+        let start = self.drain_cursor.load(Ordering::Relaxed) % self.num_topics();
+        let (first_word, first_bit) = (start / 64, start % 64);
+        let mut drained = 0;
+        for i in 0..=words {
+            if drained == budget {
+                break;
+            }
+            let w = (first_word + i) % words;
+            let window = match i {
+                0 => !0u64 << first_bit,
+                _ if i == words => (1u64 << first_bit) - 1,
+                _ => !0,
+            };
+            let word = self.pending_word(w);
+            let wanted = lowest_bits(word.load(Ordering::Relaxed) & mask.word(w) & window, budget - drained);
+            if wanted == 0 {
+                continue;
+            }
+            // Take only the bits this pass delivers; another consumer may have taken some.
+            let mut bits = word.fetch_and(!wanted, self.config.orderings.acquire()) & wanted;
+            while bits != 0 {
+                let topic_id = w * 64 + bits.trailing_zeros() as usize;
+                self.count_drain();
+                self.drain_cursor.store(topic_id + 1, Ordering::Relaxed);
+                on_topic(topic_id);
+                drained += 1;
+                bits &= bits - 1;
+            }
+        }
+        drained
+    }
+
+    /// Drain whatever is pending right now without waiting for a token, then soak up
+    /// any tokens left over so the semaphore is back in step with the bitmap.
+    ///
+    /// A consumer should call this once at startup before entering its wait loop:
+    /// if the token for already-pending topics was taken by a process that has since
+    /// died, nothing would otherwise wake it until the next 0 -> 1 transition.
+    pub fn drain_now<F: FnMut(usize)>(&self, mask: &SubscriptionMask, mut on_topic: F) {
         self.drain_pending_mask(mask, &mut on_topic);
         while self.try_wait() {
             self.drain_pending_mask(mask, &mut on_topic);
@@ -192,13 +1708,71 @@ impl Bus {
     }
 }
 
-impl Drop for Bus {
+
+/// Without feature `crypto` payloads are stored as written.
+#[cfg(not(feature = "crypto"))]
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    #[inline(always)]
+    fn seal_payload(&self, _topic_id: usize, _seq: u64, _payload: &mut [u8]) {}
+
+    #[inline(always)]
+    fn sealed(&self, _topic_id: usize, msg: Message) -> Message {
+        msg
+    }
+
+    #[inline(always)]
+    fn open_in_place(&self, _topic_id: usize, _msg: &mut Message) {}
+}
+
+/// The seq after `seq`, skipping `EMPTY_SEQ` on wrap.
+#[inline]
+pub(crate) fn next_seq(seq: u64) -> u64 {
+    match seq.wrapping_add(1) {
+        EMPTY_SEQ => 1,
+        next => next,
+    }
+}
+
+/// `BusError::ReservedSeq` unless `seq` can be written.
+#[inline]
+pub(crate) fn check_seq(seq: u64) -> Result<(), BusError> {
+    if seq == EMPTY_SEQ { Err(BusError::ReservedSeq) } else { Ok(()) }
+}
+
+/// The lowest `n` set bits of `bits`.
+#[inline]
+fn lowest_bits(mut bits: u64, n: usize) -> u64 {
+    let mut out = 0;
+    for _ in 0..n.min(64) {
+        if bits == 0 {
+            break;
+        }
+        let low = bits & bits.wrapping_neg();
+        out |= low;
+        bits ^= low;
+    }
+    out
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize> Drop
+    for SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
     fn drop(&mut self) {
-        unsafe {
-            if !self.sem.is_null() {
-                libc::sem_close(self.sem);
-                // Optional: libc::sem_unlink(c_name.as_ptr()) if you manage lifecycle externally.
-            }
+        self.flush_posts();
+        self.unregister_consumer();
+        if self.attached {
+            self.header().detach();
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Copy)]
+enum OpenMode {
+    Create,
+    /// Create, failing if the segment already exists.
+    Exclusive,
+    Attach,
+    ReadOnly,
+}
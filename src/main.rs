@@ -1,37 +1,13 @@
-pub mod lib;
-
 use std::env;
-use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 
 // Bring your types/constants into scope
-// (If your code is in a module, adjust the paths accordingly)
-use lib::{
-    Bus,
-    NUM_TOPICS, BITWORDS, NUM_EXCHANGES, NUM_MARKETS, NUM_SYMBOLS, NUM_CHANNELS,
+use ipcbus::{
+    Bus, BusError, Consumer, Message, PayloadOverrun, PayloadReader, PayloadWriter, Producer, SubscriptionMask,
+    NUM_TOPICS,
 };
 
-/// Compute a flat topic_id from (exchange, market, symbol, channel)
-#[inline]
-fn topic_id(exchange: usize, market: usize, symbol: usize, channel: usize) -> usize {
-    debug_assert!(exchange < NUM_EXCHANGES);
-    debug_assert!(market < NUM_MARKETS);
-    debug_assert!(symbol < NUM_SYMBOLS);
-    debug_assert!(channel < NUM_CHANNELS);
-
-    ((exchange * NUM_MARKETS + market) * NUM_SYMBOLS + symbol) * NUM_CHANNELS + channel
-}
-
-/// Set a single bit in the subscription mask
-#[inline]
-fn set_bit(mask: &mut [u64; BITWORDS], topic_id: usize) {
-    debug_assert!(topic_id < NUM_TOPICS);
-    let w = topic_id / 64;
-    let b = topic_id % 64;
-    mask[w] |= 1u64 << b;
-}
-
 /// Parse a comma-separated list like "1,2,3" (ignores whitespace)
 fn parse_topic_list(s: &str) -> Vec<usize> {
     s.split(',')
@@ -40,114 +16,163 @@ fn parse_topic_list(s: &str) -> Vec<usize> {
 }
 
 /// Current timestamp (ms) just for demo payloads
-fn now_ms() -> u128 {
+fn now_ms() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
-        .as_millis()
+        .as_millis() as u64
+}
+
+/// Demo quote layout: timestamp (ms), price, size
+fn decode_quote(msg: &Message) -> Result<(u64, f64, i32), PayloadOverrun> {
+    let mut r = PayloadReader::from_message(msg);
+    Ok((r.get_u64()?, r.get_f64()?, r.get_i32()?))
 }
 
 /// Producer loop: publishes payloads to a single topic at a fixed rate
 fn run_producer(bus_name: &str, topic_id: usize, rate_hz: u64) {
-    let bus = Bus::open_or_create(bus_name);
-
-    // Start after the latest observed sequence for this topic to avoid rewinding
-    let mut seq = bus.get_latest_seq(topic_id).saturating_add(1);
-
-    let interval = if rate_hz == 0 { Duration::from_millis(100) }
-                   else { Duration::from_millis(1000 / rate_hz) };
+    // Starts after the latest observed sequence for this topic to avoid rewinding
+    // A rate of 0 falls back to 10 msg/s
+    let rate_hz = if rate_hz == 0 { 10 } else { rate_hz };
+    // A second producer on the same topic would reuse this one's seqs; refuse to start
+    let mut producer = match Producer::exclusive(Bus::open_or_create(bus_name), topic_id) {
+        Ok(producer) => producer.with_rate(rate_hz),
+        Err(e) => {
+            eprintln!("[producer] bus='{}' topic_id={}: {}", bus_name, topic_id, e);
+            std::process::exit(1);
+        }
+    };
 
     println!(
         "[producer] bus='{}' topic_id={} starting seq={} rate={} msg/s",
-        bus_name, topic_id, seq, rate_hz
+        bus_name, topic_id, producer.seq(), rate_hz
     );
 
+    let mut quote = PayloadWriter::new();
     loop {
-        let payload = format!("hello from producer: seq={} ts={}ms", seq, now_ms());
-        bus.write(topic_id, seq, payload.as_bytes());
-        bus.notify(topic_id);
-
-        // For demo: print what we just wrote
-        println!("[producer] wrote topic={} seq={} '{}'", topic_id, seq, payload);
-
-        seq = seq.wrapping_add(1);
-        thread::sleep(interval);
+        // Fake quote that drifts with the seq; 20 bytes instead of a formatted string
+        let (ts, price, size) = (now_ms(), 100.0 + (producer.seq() % 100) as f64 * 0.01, 1 + (producer.seq() % 10) as i32);
+        quote.clear();
+        quote.put_u64(ts).and_then(|w| w.put_f64(price)).and_then(|w| w.put_i32(size)).expect("quote fits a slot");
+        match producer.publish(quote.written()) {
+            // For demo: print what we just wrote
+            Ok(seq) => println!("[producer] wrote topic={} seq={} ts={}ms px={:.2} sz={}", topic_id, seq, ts, price, size),
+            Err(e) => eprintln!("[producer] dropped topic={} seq={}: {}", topic_id, producer.seq(), e),
+        }
     }
 }
 
 /// Consumer loop:
 ///  - subscribes to a set of topic_ids via mask
 ///  - blocks on semaphore
-///  - drains pending topics and prints the latest message per topic
+///  - drains pending topics and prints every new message per topic
 fn run_consumer(bus_name: &str, subscribed_topics: &[usize]) {
-    let bus = Bus::open_or_create(bus_name);
-
     // Build subscription mask
-    let mut mask = [0u64; BITWORDS];
-    if subscribed_topics.is_empty() {
+    let mask = if subscribed_topics.is_empty() {
         // If nothing specified, subscribe to ALL topics
-        for t in 0..NUM_TOPICS {
-            set_bit(&mut mask, t);
-        }
         println!("[consumer] Subscribed to ALL topics ({} total)", NUM_TOPICS);
+        SubscriptionMask::all()
     } else {
-        for &t in subscribed_topics {
-            if t < NUM_TOPICS {
-                set_bit(&mut mask, t);
-            } else {
-                eprintln!("[consumer] WARNING: ignoring out-of-range topic_id {}", t);
-            }
+        for &t in subscribed_topics.iter().filter(|&&t| t >= NUM_TOPICS) {
+            eprintln!("[consumer] WARNING: ignoring out-of-range topic_id {}", t);
         }
         println!("[consumer] Subscribed to topics: {:?}", subscribed_topics);
-    }
-
-    // Keep last-seen sequence per topic to demonstrate catching up
-    let mut last_seen = vec![0u64; NUM_TOPICS];
-
-    // Drain handler: invoked for each pending topic bit
-    let mut on_topic = |t: usize| {
-        // Strategy 1 (simple demo): read only the latest message for that topic.
-        // Strategy 2 (uncomment) to walk from last_seen+1 .. latest (if you don't want to skip).
-        let latest = bus.get_latest_seq(t);
+        SubscriptionMask::from_topics(subscribed_topics)
+    };
 
-        if latest == 0 {
-            // Probably empty (or valid message with seq=0). For demo, just attempt to read latest.
+    // Wait for the producer to create the bus rather than attach to an empty one of our own
+    let bus = loop {
+        match Bus::open(bus_name) {
+            Ok(bus) => break bus,
+            Err(BusError::NotFound) => {
+                println!("[consumer] bus='{}' not found, retrying ...", bus_name);
+                std::thread::sleep(std::time::Duration::from_secs(1));
+            }
+            Err(e) => {
+                eprintln!("[consumer] cannot open bus '{}': {}", bus_name, e);
+                std::process::exit(1);
+            }
         }
-
-        // Attempt to read the latest message
-        if let Some(msg) = bus.read(t, latest) {
-            // Safety: get_text() uses from_utf8_unchecked, so ensure we wrote utf8 payloads.
-            println!(
-                "[consumer] topic={} seq={} len={} '{}'",
+    };
+    let mut consumer = Consumer::new(bus, mask);
+
+    // Drain handler: invoked for each message the consumer hasn't seen yet
+    let on_msg = |t: usize, msg: &Message| {
+        // Another process could have written anything; fall back to text (or say it isn't)
+        match decode_quote(msg) {
+            Ok((ts, price, size)) => println!(
+                "[consumer] topic={} seq={} age={:?} ts={}ms px={:.2} sz={}",
+                t,
+                msg.seq,
+                msg.age(),
+                ts,
+                price,
+                size
+            ),
+            Err(_) => println!(
+                "[consumer] topic={} seq={} len={} age={:?} '{}'",
                 t,
                 msg.seq,
                 msg.data_len,
-                msg.get_text()
-            );
-            last_seen[t] = msg.seq;
-        } else {
-            // If the slot was overwritten or not matching (race), you can scan backwards/forwards
-            // or fall back to scanning the ring. For demo we just note it.
-            // You could also iterate from last_seen[t]+1..=latest and read each if present.
-            // Example (optional):
-            /*
-            let mut s = last_seen[t].saturating_add(1);
-            while s <= latest {
-                if let Some(m) = bus.read(t, s) {
-                    println!("[consumer] topic={} seq={} '{}'", t, m.seq, m.get_text());
-                    last_seen[t] = m.seq;
-                }
-                s += 1;
-            }
-            */
+                msg.age(),
+                msg.try_get_text().unwrap_or("<non-utf8 payload>")
+            ),
         }
     };
 
     println!("[consumer] waiting for notifications on bus='{}' ...", bus_name);
 
-    // Block until at least one topic is pending; drain until the semaphore is empty
-    loop {
-        bus.wait_and_drain_mask(&mask, &mut on_topic);
+    // The first poll picks up anything published before we started; after that each
+    // poll blocks until at least one topic is pending and drains until the semaphore is empty
+    while consumer.poll(on_msg) {}
+    println!("[consumer] bus is shutting down, exiting");
+}
+
+/// One-shot snapshot of an existing bus: latest seq, length and pending state per topic
+fn run_inspect(bus_name: &str, topics: &[usize]) {
+    // Attach read-only; never create a bus (or touch its state) just to look at it
+    let bus = match Bus::open_readonly(bus_name) {
+        Ok(bus) => bus,
+        Err(e) => {
+            eprintln!("[inspect] cannot open bus '{}': {}", bus_name, e);
+            std::process::exit(1);
+        }
+    };
+
+    let topics: Vec<usize> = if topics.is_empty() {
+        bus.active_topics().collect()
+    } else {
+        topics.iter().copied().filter(|&t| t < NUM_TOPICS).collect()
+    };
+    let pending = bus.pending_snapshot();
+
+    println!(
+        "{:>5}  {:>14}  {:>12}  {:>5}  {:>7}  {:>12}  name",
+        "topic", "ex/mkt/sym/ch", "latest_seq", "len", "pending", "age"
+    );
+    let mut shown = 0;
+    for t in topics {
+        let Some(msg) = bus.read_latest(t) else { continue };
+        let (e, m, s, c) = Bus::topic_coords(t);
+        let is_pending = pending[t / 64] & (1 << (t % 64)) != 0;
+        println!(
+            "{:>5}  {:>14}  {:>12}  {:>5}  {:>7}  {:>12?}  {}",
+            t,
+            format!("{}/{}/{}/{}", e, m, s, c),
+            bus.get_latest_seq(t),
+            msg.data_len,
+            if is_pending { "yes" } else { "no" },
+            msg.age(),
+            bus.topic_name(t).unwrap_or_default()
+        );
+        shown += 1;
+    }
+    println!("{} topic(s) with data, {} pending", shown, pending.iter().map(|w| w.count_ones()).sum::<u32>());
+
+    // Roll-up per exchange feed, to spot the hottest one at a glance
+    println!("\n{:>8}  {:>12}  {:>12}  {:>10}", "exchange", "writes", "notifies", "overflows");
+    for (e, ex) in bus.stats_by_exchange().iter().enumerate() {
+        println!("{:>8}  {:>12}  {:>12}  {:>10}", e, ex.writes, ex.notifies, ex.overflows);
     }
 }
 
@@ -156,6 +181,7 @@ fn print_usage(program: &str) {
         r#"Usage:
   {prog} producer <bus_name> <topic_id> [rate_hz]
   {prog} consumer <bus_name> [topic_list]
+  {prog} inspect <bus_name> [topic_list]
 
 Examples:
   # Producer writes to topic 42 at 4 msgs/sec
@@ -166,6 +192,9 @@ Examples:
 
   # Consumer subscribes to ALL topics (omit topic_list)
   {prog} consumer mybus
+
+  # Print latest seq / length / pending state of every topic with data
+  {prog} inspect mybus
 "#,
         prog = program
     );
@@ -203,6 +232,11 @@ fn main() {
             };
             run_consumer(bus_name, &topics);
         }
+        "inspect" => {
+            let bus_name = &args[2];
+            let topics: Vec<usize> = args.get(3).map(|a| parse_topic_list(a)).unwrap_or_default();
+            run_inspect(bus_name, &topics);
+        }
         _ => {
             print_usage(&args[0]);
         }
@@ -0,0 +1,235 @@
+use core::ops::Range;
+
+use crate::{Bus, BITWORDS, NUM_CHANNELS, NUM_EXCHANGES, NUM_MARKETS, NUM_SYMBOLS, NUM_TOPICS};
+
+/// Set of topic_ids a consumer is interested in, laid out exactly like
+/// the journal's pending bitmap so draining is a per-word AND. It holds only the
+/// words up to its highest topic; missing words read as 0, so one mask works on a
+/// segment of any topic count.
+#[derive(Clone, Debug, Default)]
+pub struct SubscriptionMask {
+    words: Vec<u64>,
+}
+
+impl SubscriptionMask {
+    /// Empty mask (subscribed to nothing).
+    #[inline]
+    pub const fn new() -> Self {
+        Self { words: Vec::new() }
+    }
+
+    /// Mask with every valid topic_id set. Bits past NUM_TOPICS in the last word stay clear.
+    pub fn all() -> Self {
+        let mut mask = Self { words: vec![u64::MAX; BITWORDS] };
+        let tail = NUM_TOPICS % 64;
+        if tail != 0 {
+            mask.words[BITWORDS - 1] = (1u64 << tail) - 1;
+        }
+        mask
+    }
+
+    /// Build a mask from a list of topic_ids, silently ignoring out-of-range ids.
+    pub fn from_topics(topics: &[usize]) -> Self {
+        let mut mask = Self::new();
+        for &t in topics {
+            if t < NUM_TOPICS {
+                mask.set(t);
+            }
+        }
+        mask
+    }
+
+    /// Every topic except `topics`, e.g. the firehose minus a few noisy ones.
+    /// Out-of-range ids are ignored, as in `from_topics`.
+    pub fn all_except(topics: &[usize]) -> Self {
+        Self::all().difference(&Self::from_topics(topics))
+    }
+
+    #[inline]
+    pub fn set(&mut self, topic_id: usize) -> &mut Self {
+        debug_assert!(topic_id < NUM_TOPICS);
+        if topic_id < NUM_TOPICS {
+            let w = topic_id / 64;
+            if w >= self.words.len() {
+                self.words.resize(w + 1, 0);
+            }
+            self.words[w] |= 1u64 << (topic_id % 64);
+        }
+        self
+    }
+
+    #[inline]
+    pub fn clear(&mut self, topic_id: usize) -> &mut Self {
+        debug_assert!(topic_id < NUM_TOPICS);
+        if let Some(word) = self.words.get_mut(topic_id / 64) {
+            *word &= !(1u64 << (topic_id % 64));
+        }
+        self
+    }
+
+    /// Set every topic matching the given coordinates, where `None` means
+    /// "all values of this dimension". Out-of-range coordinates match nothing.
+    ///
+    /// e.g. every channel-0 topic on exchange 2: `set_wildcard(Some(2), None, None, Some(0))`
+    pub fn set_wildcard(
+        &mut self,
+        exchange: Option<usize>,
+        market: Option<usize>,
+        symbol: Option<usize>,
+        channel: Option<usize>,
+    ) -> &mut Self {
+        for_each_matching(exchange, market, symbol, channel, |t| {
+            self.set(t);
+        });
+        self
+    }
+
+    /// Clear every topic matching the coordinates, with `None` as in `set_wildcard`.
+    ///
+    /// e.g. everything but exchange 4: start from `all()`, then
+    /// `clear_wildcard(Some(4), None, None, None)`.
+    pub fn clear_wildcard(
+        &mut self,
+        exchange: Option<usize>,
+        market: Option<usize>,
+        symbol: Option<usize>,
+        channel: Option<usize>,
+    ) -> &mut Self {
+        for_each_matching(exchange, market, symbol, channel, |t| {
+            self.clear(t);
+        });
+        self
+    }
+
+    #[inline]
+    pub fn contains(&self, topic_id: usize) -> bool {
+        topic_id < NUM_TOPICS && (self.word(topic_id / 64) >> (topic_id % 64)) & 1 == 1
+    }
+
+    /// Topics subscribed in either mask.
+    pub fn union(&self, other: &Self) -> Self {
+        let len = self.words.len().max(other.words.len());
+        Self { words: (0..len).map(|w| self.word(w) | other.word(w)).collect() }
+    }
+
+    /// Topics subscribed in this mask but not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut mask = self.clone();
+        for (w, o) in mask.words.iter_mut().zip(other.words.iter()) {
+            *w &= !o;
+        }
+        mask
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    /// Raw words, in the same layout as `Journal::pending_bits`, up to the last one
+    /// the mask has grown to.
+    #[inline]
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Word `w`, or 0 past the end of `words`.
+    #[inline]
+    pub fn word(&self, w: usize) -> u64 {
+        self.words.get(w).copied().unwrap_or(0)
+    }
+
+    /// Mask from raw words in the `words` layout; bits that don't map to a topic are
+    /// dropped.
+    pub fn from_words(words: &[u64]) -> Self {
+        let all = Self::all();
+        Self { words: words.iter().take(BITWORDS).enumerate().map(|(w, &bits)| bits & all.words[w]).collect() }
+    }
+}
+
+impl PartialEq for SubscriptionMask {
+    fn eq(&self, other: &Self) -> bool {
+        (0..self.words.len().max(other.words.len())).all(|w| self.word(w) == other.word(w))
+    }
+}
+
+impl Eq for SubscriptionMask {}
+
+impl IntoIterator for SubscriptionMask {
+    type Item = usize;
+    type IntoIter = Topics;
+
+    /// Set topic_ids in ascending order.
+    fn into_iter(self) -> Topics {
+        Topics { words: self.words, w: 0 }
+    }
+}
+
+impl IntoIterator for &SubscriptionMask {
+    type Item = usize;
+    type IntoIter = Topics;
+
+    fn into_iter(self) -> Topics {
+        self.clone().into_iter()
+    }
+}
+
+/// Iterator over the topic_ids set in a `SubscriptionMask`, lowest first.
+#[derive(Clone, Debug)]
+pub struct Topics {
+    words: Vec<u64>,
+    w: usize,
+}
+
+impl Iterator for Topics {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.w < self.words.len() {
+            let bits = self.words[self.w];
+            if bits != 0 {
+                self.words[self.w] = bits & (bits - 1);
+                return Some(self.w * 64 + bits.trailing_zeros() as usize);
+            }
+            self.w += 1;
+        }
+        None
+    }
+}
+
+impl From<[u64; BITWORDS]> for SubscriptionMask {
+    fn from(words: [u64; BITWORDS]) -> Self {
+        Self::from_words(&words)
+    }
+}
+
+/// Range of values selected in one dimension: a single value, or all of them for `None`.
+#[inline]
+fn dim(selector: Option<usize>, len: usize) -> Range<usize> {
+    match selector {
+        Some(v) => {
+            debug_assert!(v < len);
+            if v < len { v..v + 1 } else { 0..0 }
+        }
+        None => 0..len,
+    }
+}
+
+/// Invoke `f` with every topic_id in the cartesian product of the selected coordinates.
+fn for_each_matching<F: FnMut(usize)>(
+    exchange: Option<usize>,
+    market: Option<usize>,
+    symbol: Option<usize>,
+    channel: Option<usize>,
+    mut f: F,
+) {
+    for e in dim(exchange, NUM_EXCHANGES) {
+        for m in dim(market, NUM_MARKETS) {
+            for s in dim(symbol, NUM_SYMBOLS) {
+                for c in dim(channel, NUM_CHANNELS) {
+                    f(Bus::topic_id(e, m, s, c));
+                }
+            }
+        }
+    }
+}
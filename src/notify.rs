@@ -0,0 +1,534 @@
+//! Cross-process wakeup primitive behind `Bus::notify` / `Bus::wait`.
+//!
+//! Every backend implements the `Notifier` trait, a counting semaphore in all but
+//! name, and `open` picks one from the config; the `Bus` only ever holds a
+//! `dyn Notifier`, so the drain logic is shared by all of them.
+//!
+//! Unix uses a POSIX named semaphore; Windows uses a named auto-reset Event.
+//! An Event doesn't count, but the pending bitmap does the real bookkeeping:
+//! a consumer drains every set bit per wakeup, so one signal per 0 -> 1
+//! transition is all either backend needs. On Linux a named FIFO can stand in
+//! for the semaphore when the wakeup has to be pollable (`NotifyBackend::Fifo`),
+//! or a futex on a word in the journal when no named object is wanted at all
+//! (`NotifyBackend::Futex`).
+//!
+//! Each consumer group has its own primitive; group 0 keeps the plain names and
+//! group `g` appends `-g{g}` to them.
+//!
+//! A `Backing::Heap` bus (feature `testing`) ignores the backend and uses the
+//! Condvar semaphores of its heap segment, so it never touches a named object.
+
+use std::io;
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::BusConfig;
+
+/// How `notify` wakes consumers. Like the semaphore name, every process on a bus
+/// must use the same backend.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum NotifyBackend {
+    /// The named semaphore (Event on Windows) `{sem_prefix}{name}-notify`.
+    #[default]
+    Semaphore,
+    /// A named FIFO at this path, created if missing, where each post is one byte.
+    /// Unlike a semaphore its fd can be registered with epoll (see `Bus::notify_fd`).
+    /// An eventfd would be cheaper but can't be opened by name from another process.
+    #[cfg(target_os = "linux")]
+    Fifo(PathBuf),
+    /// A token count in the journal itself (`Journal::futex`), slept on with
+    /// `FUTEX_WAIT`. Nothing to create, name or unlink besides the segment; the
+    /// primitive lives and dies with it. Waiting stores to the segment, so
+    /// `open_readonly` refuses it.
+    #[cfg(target_os = "linux")]
+    Futex,
+}
+
+/// One group's wakeup primitive. `post` hands out a token and the waits take one;
+/// a backend that can't count (the Windows Event) may coalesce posts.
+pub(crate) trait Notifier: Send + Sync {
+    fn post(&self);
+
+    /// Block until a token is available and take it.
+    fn wait(&self);
+
+    /// Take a token if one is available, without blocking.
+    fn try_wait(&self) -> bool;
+
+    /// Block for at most `timeout`; true if a token was taken.
+    fn wait_timeout(&self, timeout: Duration) -> bool;
+
+    /// Tokens currently waiting to be taken, for backends that can tell.
+    fn value(&self) -> Option<i32> {
+        None
+    }
+
+    /// An fd readable while a token is waiting, for backends that have one.
+    #[cfg(target_os = "linux")]
+    fn fd(&self) -> Option<std::os::fd::RawFd> {
+        None
+    }
+}
+
+/// Open (creating if needed) group `group`'s primitive of bus `name`.
+pub(crate) fn open(name: &str, config: &BusConfig, group: usize) -> io::Result<Box<dyn Notifier>> {
+    #[cfg(feature = "testing")]
+    if config.backing == crate::Backing::Heap {
+        return Ok(Box::new(crate::heap::semaphore(name, group)?));
+    }
+    match &config.notify_backend {
+        #[cfg(unix)]
+        NotifyBackend::Semaphore => Ok(Box::new(posix::Semaphore::open(name, config, group)?)),
+        #[cfg(windows)]
+        NotifyBackend::Semaphore => Ok(Box::new(win::Event::open(name, config, group)?)),
+        #[cfg(target_os = "linux")]
+        NotifyBackend::Fifo(path) => {
+            let mut path = path.clone().into_os_string();
+            path.push(group_suffix(group));
+            Ok(Box::new(posix::fifo::Fifo::open(path.as_ref(), config.sem_mode)?))
+        }
+        #[cfg(target_os = "linux")]
+        NotifyBackend::Futex => Ok(Box::new(posix::futex::FutexSem::open(name, config, group)?)),
+    }
+}
+
+/// Appended to group `g`'s primitive name; empty for the default group.
+fn group_suffix(group: usize) -> String {
+    match group {
+        0 => String::new(),
+        g => format!("-g{}", g),
+    }
+}
+
+/// Remove the named primitives of every group of bus `name` (missing ones are
+/// skipped). Handles already open keep working; later opens get fresh ones.
+#[cfg(unix)]
+pub(crate) fn unlink(name: &str, config: &BusConfig) {
+    #[cfg(feature = "testing")]
+    if config.backing == crate::Backing::Heap {
+        return;
+    }
+    for g in 0..crate::NUM_GROUPS {
+        match &config.notify_backend {
+            NotifyBackend::Semaphore => {
+                let Ok(sem_name) = std::ffi::CString::new(format!("{}{}-notify{}", config.sem_prefix, name, group_suffix(g)))
+                else {
+                    return;
+                };
+                unsafe {
+                    libc::sem_unlink(sem_name.as_ptr());
+                }
+            }
+            #[cfg(target_os = "linux")]
+            NotifyBackend::Fifo(path) => {
+                let mut path = path.clone().into_os_string();
+                path.push(group_suffix(g));
+                let _ = std::fs::remove_file(path);
+            }
+            #[cfg(target_os = "linux")]
+            NotifyBackend::Futex => {}
+        }
+    }
+}
+
+/// Events are destroyed with their last handle; there is no name to remove.
+#[cfg(windows)]
+pub(crate) fn unlink(_name: &str, _config: &BusConfig) {}
+
+#[cfg(unix)]
+mod posix {
+    use std::ffi::CString;
+    use std::io;
+    use std::time::Duration;
+
+    use super::Notifier;
+    use crate::BusConfig;
+
+    /// POSIX named semaphore (works on macOS & Linux).
+    pub(crate) struct Semaphore {
+        sem: *mut libc::sem_t,
+    }
+
+    // A sem_t handle may be used from any thread.
+    unsafe impl Send for Semaphore {}
+    unsafe impl Sync for Semaphore {}
+
+    impl Semaphore {
+        pub(crate) fn open(name: &str, config: &BusConfig, group: usize) -> io::Result<Self> {
+            // Name MUST start with '/'.
+            let sem_name = CString::new(format!("{}{}-notify{}", config.sem_prefix, name, super::group_suffix(group)))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let mode = config.sem_mode as libc::c_uint;
+            let sem = unsafe { libc::sem_open(sem_name.as_ptr(), libc::O_CREAT, mode, 0) };
+            if sem == libc::SEM_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { sem })
+        }
+    }
+
+    impl Notifier for Semaphore {
+        #[inline]
+        fn post(&self) {
+            unsafe {
+                libc::sem_post(self.sem);
+            }
+        }
+
+        #[inline]
+        fn wait(&self) {
+            unsafe {
+                // Will block until someone sem_post()s.
+                libc::sem_wait(self.sem);
+            }
+        }
+
+        #[inline]
+        fn try_wait(&self) -> bool {
+            unsafe { libc::sem_trywait(self.sem) == 0 }
+        }
+
+        #[cfg(not(target_vendor = "apple"))]
+        fn wait_timeout(&self, timeout: Duration) -> bool {
+            // sem_timedwait takes an absolute CLOCK_REALTIME deadline.
+            let mut now = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+            unsafe {
+                libc::clock_gettime(libc::CLOCK_REALTIME, &mut now);
+            }
+            let deadline = Duration::new(now.tv_sec as u64, now.tv_nsec as u32) + timeout;
+            let abs = libc::timespec {
+                tv_sec: deadline.as_secs() as libc::time_t,
+                tv_nsec: deadline.subsec_nanos() as libc::c_long,
+            };
+            loop {
+                if unsafe { libc::sem_timedwait(self.sem, &abs) } == 0 {
+                    return true;
+                }
+                if io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+                    return false;
+                }
+            }
+        }
+
+        /// macOS has no sem_timedwait, so poll with a short sleep instead.
+        #[cfg(target_vendor = "apple")]
+        fn wait_timeout(&self, timeout: Duration) -> bool {
+            use std::time::Instant;
+
+            let deadline = Instant::now() + timeout;
+            loop {
+                if self.try_wait() {
+                    return true;
+                }
+                let now = Instant::now();
+                if now >= deadline {
+                    return false;
+                }
+                std::thread::sleep((deadline - now).min(Duration::from_micros(200)));
+            }
+        }
+
+        /// macOS declares `sem_getvalue` but doesn't implement it for named semaphores.
+        #[cfg(not(target_vendor = "apple"))]
+        fn value(&self) -> Option<i32> {
+            let mut value = 0;
+            (unsafe { libc::sem_getvalue(self.sem, &mut value) } == 0).then_some(value)
+        }
+    }
+
+    impl Drop for Semaphore {
+        fn drop(&mut self) {
+            unsafe {
+                // Other processes may still use the name; `OwnedBus` and `reap_stale` unlink it.
+                libc::sem_close(self.sem);
+            }
+        }
+    }
+
+    /// Named pipe used as a counting semaphore: a post writes one byte, a wait reads one.
+    #[cfg(target_os = "linux")]
+    pub(crate) mod fifo {
+        use std::ffi::CString;
+        use std::io;
+        use std::os::fd::RawFd;
+        use std::os::unix::ffi::OsStrExt;
+        use std::path::Path;
+        use std::time::{Duration, Instant};
+
+        use crate::notify::Notifier;
+
+        pub(crate) struct Fifo {
+            fd: RawFd,
+        }
+
+        impl Fifo {
+            pub(crate) fn open(path: &Path, mode: u32) -> io::Result<Self> {
+                let c_path = CString::new(path.as_os_str().as_bytes())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                if unsafe { libc::mkfifo(c_path.as_ptr(), mode as libc::mode_t) } != 0 {
+                    let err = io::Error::last_os_error();
+                    if err.raw_os_error() != Some(libc::EEXIST) {
+                        return Err(err);
+                    }
+                }
+                // Linux lets a FIFO be opened O_RDWR, so neither side blocks in open or
+                // sees EOF/EPIPE when the other side has no process attached yet.
+                let flags = libc::O_RDWR | libc::O_NONBLOCK | libc::O_CLOEXEC;
+                let fd = unsafe { libc::open(c_path.as_ptr(), flags) };
+                if fd < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(Self { fd })
+            }
+
+            /// Poll up to `timeout_ms` (-1 blocks) and take a byte. False on timeout, or
+            /// if another waiter took the byte between the wakeup and the read.
+            fn wait_for(&self, timeout_ms: i32) -> bool {
+                let mut pfd = libc::pollfd { fd: self.fd, events: libc::POLLIN, revents: 0 };
+                unsafe { libc::poll(&mut pfd, 1, timeout_ms) > 0 && self.try_wait() }
+            }
+        }
+
+        impl Notifier for Fifo {
+            /// A full pipe drops the byte, which is fine: 64 KiB of unconsumed posts
+            /// already guarantee the consumer won't sleep through the pending bits.
+            #[inline]
+            fn post(&self) {
+                unsafe {
+                    libc::write(self.fd, [1u8].as_ptr().cast(), 1);
+                }
+            }
+
+            #[inline]
+            fn wait(&self) {
+                while !self.wait_for(-1) {}
+            }
+
+            #[inline]
+            fn try_wait(&self) -> bool {
+                let mut byte = 0u8;
+                unsafe { libc::read(self.fd, (&raw mut byte).cast(), 1) == 1 }
+            }
+
+            fn wait_timeout(&self, timeout: Duration) -> bool {
+                let deadline = Instant::now() + timeout;
+                loop {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    // Round up so a sub-millisecond remainder still waits.
+                    let ms = left.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32;
+                    if self.wait_for(ms) {
+                        return true;
+                    }
+                    if left.is_zero() {
+                        return false;
+                    }
+                }
+            }
+
+            /// Each unconsumed post is one byte in the pipe.
+            fn value(&self) -> Option<i32> {
+                let mut bytes: libc::c_int = 0;
+                (unsafe { libc::ioctl(self.fd, libc::FIONREAD, &mut bytes) } == 0).then_some(bytes)
+            }
+
+            /// The FIFO's fd, readable while a post is waiting to be consumed.
+            fn fd(&self) -> Option<RawFd> {
+                Some(self.fd)
+            }
+        }
+
+        impl Drop for Fifo {
+            fn drop(&mut self) {
+                unsafe {
+                    libc::close(self.fd);
+                }
+            }
+        }
+    }
+
+    /// Counting semaphore on a `[tokens, waiters]` pair in the journal. Posters only
+    /// make the `FUTEX_WAKE` syscall when someone has announced itself as waiting.
+    #[cfg(target_os = "linux")]
+    pub(crate) mod futex {
+        use core::sync::atomic::{AtomicU32, Ordering};
+        use std::io;
+        use std::sync::Arc;
+        use std::time::{Duration, Instant};
+
+        use crate::backing::{self, SharedMapping};
+        use crate::notify::Notifier;
+        use crate::{BusConfig, Journal, NUM_GROUPS};
+
+        pub(crate) struct FutexSem {
+            /// Holds the mapping, so a waiter parked on another thread (e.g. by
+            /// `wait_async`) can't outlive the memory it sleeps on.
+            _mapping: Arc<SharedMapping>,
+            tokens: *const AtomicU32,
+            waiters: *const AtomicU32,
+        }
+
+        // The words are atomics in a shared mapping that lives as long as `self`.
+        unsafe impl Send for FutexSem {}
+        unsafe impl Sync for FutexSem {}
+
+        impl FutexSem {
+            pub(crate) fn open(name: &str, config: &BusConfig, group: usize) -> io::Result<Self> {
+                let (mapping, _) =
+                    backing::map_shared(name, &config.backing, 0, crate::OpenMode::Attach, config.fixed_addr)
+                        .map_err(|e| io::Error::other(e.to_string()))?;
+                // The same offset in every `Journal`; see `Journal::futex`.
+                let offset = core::mem::offset_of!(Journal, futex);
+                if mapping.len() < offset + core::mem::size_of::<[[u32; 2]; NUM_GROUPS]>() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "segment too small for the futex words"));
+                }
+                let pair = unsafe { mapping.as_ptr().add(offset).cast::<[u32; 2]>().add(group) };
+                let tokens = pair.cast::<AtomicU32>();
+                Ok(Self { _mapping: mapping, tokens, waiters: unsafe { tokens.add(1) } })
+            }
+
+            #[inline]
+            fn tokens(&self) -> &AtomicU32 {
+                unsafe { &*self.tokens }
+            }
+
+            #[inline]
+            fn waiters(&self) -> &AtomicU32 {
+                unsafe { &*self.waiters }
+            }
+
+            /// Take a token, sleeping until `deadline` (forever if None). False on timeout.
+            fn wait_until(&self, deadline: Option<Instant>) -> bool {
+                loop {
+                    if self.try_wait() {
+                        return true;
+                    }
+                    let timeout = match deadline {
+                        None => None,
+                        Some(d) => match d.saturating_duration_since(Instant::now()) {
+                            Duration::ZERO => return false,
+                            left => Some(libc::timespec {
+                                tv_sec: left.as_secs() as libc::time_t,
+                                tv_nsec: left.subsec_nanos() as libc::c_long,
+                            }),
+                        },
+                    };
+                    self.waiters().fetch_add(1, Ordering::SeqCst);
+                    if self.tokens().load(Ordering::SeqCst) == 0 {
+                        let ts = timeout.as_ref().map_or(core::ptr::null(), |t| t as *const libc::timespec);
+                        // Returns at once if a post already moved the count off 0.
+                        unsafe {
+                            libc::syscall(libc::SYS_futex, self.tokens, libc::FUTEX_WAIT, 0, ts);
+                        }
+                    }
+                    self.waiters().fetch_sub(1, Ordering::SeqCst);
+                }
+            }
+        }
+
+        impl Notifier for FutexSem {
+            #[inline]
+            fn post(&self) {
+                // SeqCst pairs with the waiter's announce-then-recheck in `wait_until`:
+                // either it sees the token, or this load sees it waiting and wakes it.
+                self.tokens().fetch_add(1, Ordering::SeqCst);
+                if self.waiters().load(Ordering::SeqCst) != 0 {
+                    unsafe {
+                        libc::syscall(libc::SYS_futex, self.tokens, libc::FUTEX_WAKE, 1);
+                    }
+                }
+            }
+
+            #[inline]
+            fn wait(&self) {
+                self.wait_until(None);
+            }
+
+            #[inline]
+            fn try_wait(&self) -> bool {
+                self.tokens()
+                    .fetch_update(Ordering::Acquire, Ordering::Relaxed, |t| t.checked_sub(1))
+                    .is_ok()
+            }
+
+            fn wait_timeout(&self, timeout: Duration) -> bool {
+                self.wait_until(Some(Instant::now() + timeout))
+            }
+
+            fn value(&self) -> Option<i32> {
+                Some(self.tokens().load(Ordering::Relaxed).min(i32::MAX as u32) as i32)
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod win {
+    use std::io;
+    use std::time::Duration;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0};
+    use windows_sys::Win32::System::Threading::{CreateEventW, INFINITE, SetEvent, WaitForSingleObject};
+
+    use super::Notifier;
+    use crate::BusConfig;
+
+    /// Named auto-reset Event.
+    pub(crate) struct Event {
+        event: HANDLE,
+    }
+
+    // Event handles may be waited on / signalled from any thread.
+    unsafe impl Send for Event {}
+    unsafe impl Sync for Event {}
+
+    impl Event {
+        pub(crate) fn open(name: &str, config: &BusConfig, group: usize) -> io::Result<Self> {
+            // Creates the event, or opens it if another process already did.
+            let wide: Vec<u16> = format!("{}{}-notify{}", config.sem_prefix, name, super::group_suffix(group))
+                .encode_utf16()
+                .chain(Some(0))
+                .collect();
+            let event = unsafe { CreateEventW(core::ptr::null(), 0, 0, wide.as_ptr()) };
+            if event.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { event })
+        }
+    }
+
+    impl Notifier for Event {
+        #[inline]
+        fn post(&self) {
+            unsafe {
+                SetEvent(self.event);
+            }
+        }
+
+        #[inline]
+        fn wait(&self) {
+            unsafe {
+                WaitForSingleObject(self.event, INFINITE);
+            }
+        }
+
+        #[inline]
+        fn try_wait(&self) -> bool {
+            unsafe { WaitForSingleObject(self.event, 0) == WAIT_OBJECT_0 }
+        }
+
+        /// Block for at most `timeout`; true if the event was signalled.
+        fn wait_timeout(&self, timeout: Duration) -> bool {
+            let ms = timeout.as_millis().min(INFINITE as u128 - 1) as u32;
+            unsafe { WaitForSingleObject(self.event, ms) == WAIT_OBJECT_0 }
+        }
+    }
+
+    impl Drop for Event {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.event);
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Memory orderings of the hot paths, selectable per handle.
+//!
+//! The notify/drain handshake and the slot seqlock only need Release on the
+//! publishing side and Acquire on the observing side. `OrderingProfile` lets an
+//! expert trade that for `SeqCst` (to rule orderings out while debugging) or
+//! `Relaxed` (to measure what they cost on a given machine). Only the default is
+//! correct everywhere; see `BusConfig::orderings`.
+
+use core::sync::atomic::{fence, Ordering};
+
+/// Orderings used by `notify`, the drain calls and the slot seqlock.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OrderingProfile {
+    /// Release to publish a slot or pending bit, Acquire to observe it. Correct on
+    /// weakly-ordered targets such as aarch64.
+    #[default]
+    AcquireRelease,
+    /// `SeqCst` wherever the default uses Release or Acquire, fences included.
+    SeqCst,
+    /// `Relaxed` throughout and no fences. A consumer can then see a pending bit
+    /// before the message it announces, and a seqlock read can pass its version
+    /// check with a torn payload: even on x86 the compiler is free to reorder.
+    Relaxed,
+}
+
+impl OrderingProfile {
+    /// Ordering for the store that publishes (a version, a pending bit).
+    #[inline]
+    pub(crate) fn release(self) -> Ordering {
+        match self {
+            Self::AcquireRelease => Ordering::Release,
+            Self::SeqCst => Ordering::SeqCst,
+            Self::Relaxed => Ordering::Relaxed,
+        }
+    }
+
+    /// Ordering for the load or read-modify-write that observes a publish.
+    #[inline]
+    pub(crate) fn acquire(self) -> Ordering {
+        match self {
+            Self::AcquireRelease => Ordering::Acquire,
+            Self::SeqCst => Ordering::SeqCst,
+            Self::Relaxed => Ordering::Relaxed,
+        }
+    }
+
+    /// Release fence, or none at all under `Relaxed`.
+    #[inline]
+    pub(crate) fn fence_release(self) {
+        if self != Self::Relaxed {
+            fence(self.release());
+        }
+    }
+
+    /// Acquire fence, or none at all under `Relaxed`.
+    #[inline]
+    pub(crate) fn fence_acquire(self) {
+        if self != Self::Relaxed {
+            fence(self.acquire());
+        }
+    }
+}
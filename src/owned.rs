@@ -0,0 +1,106 @@
+//! Buses whose names are removed when their creator is done with them.
+//!
+//! A plain `Bus` never unlinks its semaphores (other processes may still be using
+//! them), and a file-backed segment stays on disk, so short-lived processes leave
+//! names behind in `/dev/shm`. An `OwnedBus` is the one creator of its segment and
+//! cleans up everything on drop; `reap_stale` removes what an owner that died
+//! without dropping left behind.
+
+use core::ops::Deref;
+
+use crate::{
+    backing, notify, BusConfig, BusError, OpenMode, SizedBus, BITWORDS, MAX_DATA_SIZE, MAX_SLOTS_PER_TOPIC,
+    NUM_TOPICS,
+};
+
+/// The sole creator of a bus. Derefs to the `Bus`; dropping it unlinks the
+/// segment and every group's notify primitive. Processes still attached keep
+/// working on the old objects, but nothing new can find them.
+pub struct OwnedBus<
+    const TOPICS: usize = NUM_TOPICS,
+    const SLOTS: usize = MAX_SLOTS_PER_TOPIC,
+    const DATA: usize = MAX_DATA_SIZE,
+    const WORDS: usize = BITWORDS,
+> {
+    bus: SizedBus<TOPICS, SLOTS, DATA, WORDS>,
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize> Deref
+    for OwnedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    type Target = SizedBus<TOPICS, SLOTS, DATA, WORDS>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.bus
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize> Drop
+    for OwnedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    fn drop(&mut self) {
+        unlink_all(&self.bus.name, &self.bus.config);
+    }
+}
+
+fn unlink_all(name: &str, config: &BusConfig) {
+    backing::unlink(name, &config.backing);
+    notify::unlink(name, config);
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Create bus `name` as its only owner: fails with `BusError::AlreadyExists`
+    /// instead of attaching if the segment is already there, and records this
+    /// process's pid in the header for `reap_stale`.
+    pub fn create_exclusive(name: &str) -> Result<OwnedBus<TOPICS, SLOTS, DATA, WORDS>, BusError> {
+        Self::create_exclusive_with(name, &BusConfig::default())
+    }
+
+    /// `create_exclusive` with a config.
+    pub fn create_exclusive_with(
+        name: &str,
+        config: &BusConfig,
+    ) -> Result<OwnedBus<TOPICS, SLOTS, DATA, WORDS>, BusError> {
+        Self::open_inner(name, config, OpenMode::Exclusive, 0).map(|bus| OwnedBus { bus })
+    }
+
+    /// Unlink bus `name` if it was made by `create_exclusive` in a process that no
+    /// longer exists. Returns whether it did; a live owner, a bus with no recorded
+    /// owner, or no bus at all is left alone.
+    #[cfg(unix)]
+    pub fn reap_stale(name: &str) -> Result<bool, BusError> {
+        Self::reap_stale_with(name, &BusConfig::default())
+    }
+
+    /// `reap_stale` with the backing and notify naming of `config`.
+    #[cfg(unix)]
+    pub fn reap_stale_with(name: &str, config: &BusConfig) -> Result<bool, BusError> {
+        let owner = match Self::open_readonly_with(name, config) {
+            Ok(bus) => bus.header().owner_pid(),
+            Err(BusError::NotFound) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let dead = owner != 0 && !process_exists(owner);
+        if dead {
+            unlink_all(name, config);
+        }
+        Ok(dead)
+    }
+}
+
+/// False only if `pid` is known not to exist. Unix probes it with `kill(pid, 0)`
+/// (EPERM means it exists but belongs to someone else); elsewhere every pid is
+/// assumed alive.
+pub(crate) fn process_exists(pid: u32) -> bool {
+    #[cfg(unix)]
+    let exists = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0
+        || std::io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH);
+    #[cfg(not(unix))]
+    let exists = {
+        let _ = pid;
+        true
+    };
+    exists
+}
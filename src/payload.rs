@@ -0,0 +1,118 @@
+//! Little-endian cursors for fixed-layout numeric payloads.
+//!
+//! A producer writes fields in order with `PayloadWriter` and publishes
+//! `written()`; the consumer reads them back in the same order with
+//! `PayloadReader`. There is no framing or schema: both sides agree on the field
+//! order (`Message::msg_type` is a natural place to tell layouts apart).
+
+use crate::{Message, PayloadOverrun, DATA_SIZE};
+
+/// Writes primitives into a buffer, by default a slot-sized `[u8; DATA_SIZE]`.
+pub struct PayloadWriter<B = [u8; DATA_SIZE]> {
+    buf: B,
+    pos: usize,
+}
+
+impl PayloadWriter {
+    /// Writer over a fresh `DATA_SIZE` buffer.
+    pub fn new() -> Self {
+        Self::over([0u8; DATA_SIZE])
+    }
+}
+
+impl Default for PayloadWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: AsMut<[u8]> + AsRef<[u8]>> PayloadWriter<B> {
+    /// Writer over a caller's buffer, e.g. a `&mut [u8]` reused across messages.
+    pub fn over(buf: B) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    #[inline]
+    fn put<const N: usize>(&mut self, bytes: [u8; N]) -> Result<&mut Self, PayloadOverrun> {
+        let buf = self.buf.as_mut();
+        let remaining = buf.len() - self.pos;
+        if N > remaining {
+            return Err(PayloadOverrun { offset: self.pos, need: N, remaining });
+        }
+        buf[self.pos..self.pos + N].copy_from_slice(&bytes);
+        self.pos += N;
+        Ok(self)
+    }
+
+    pub fn put_u64(&mut self, v: u64) -> Result<&mut Self, PayloadOverrun> {
+        self.put(v.to_le_bytes())
+    }
+
+    pub fn put_i32(&mut self, v: i32) -> Result<&mut Self, PayloadOverrun> {
+        self.put(v.to_le_bytes())
+    }
+
+    pub fn put_f64(&mut self, v: f64) -> Result<&mut Self, PayloadOverrun> {
+        self.put(v.to_le_bytes())
+    }
+
+    /// The bytes written so far: what to hand to `write` / `publish`.
+    #[inline]
+    pub fn written(&self) -> &[u8] {
+        &self.buf.as_ref()[..self.pos]
+    }
+
+    /// Start over, keeping the buffer.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.pos = 0;
+    }
+}
+
+/// Reads primitives back out of a payload, in the order they were written.
+#[derive(Clone, Debug)]
+pub struct PayloadReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PayloadReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// Reader over `msg.payload()`.
+    pub fn from_message(msg: &'a Message) -> Self {
+        Self::new(msg.payload())
+    }
+
+    #[inline]
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], PayloadOverrun> {
+        let remaining = self.remaining();
+        if N > remaining {
+            return Err(PayloadOverrun { offset: self.pos, need: N, remaining });
+        }
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&self.buf[self.pos..self.pos + N]);
+        self.pos += N;
+        Ok(bytes)
+    }
+
+    pub fn get_u64(&mut self) -> Result<u64, PayloadOverrun> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    pub fn get_i32(&mut self) -> Result<i32, PayloadOverrun> {
+        self.take().map(i32::from_le_bytes)
+    }
+
+    pub fn get_f64(&mut self) -> Result<f64, PayloadOverrun> {
+        self.take().map(f64::from_le_bytes)
+    }
+
+    /// Bytes not yet read.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+}
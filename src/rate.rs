@@ -0,0 +1,59 @@
+//! Pacing for producers that must not outrun a target message rate.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{Clock, MonotonicClock};
+
+/// Spaces calls at least `1 / hz` apart on its clock. Idle time isn't banked, so a
+/// producer that pauses doesn't get to burst afterwards.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    interval_ns: u64,
+    /// Earliest clock time the next call may go at.
+    next_ns: u64,
+    throttled: u64,
+    clock: Arc<dyn Clock>,
+}
+
+impl RateLimiter {
+    /// Panics if `hz` is 0.
+    pub fn new(hz: u64) -> Self {
+        Self::with_clock(hz, Arc::new(MonotonicClock))
+    }
+
+    /// Pace on `clock`; with a `MockClock`, `pace` advances it instead of sleeping.
+    pub fn with_clock(hz: u64, clock: Arc<dyn Clock>) -> Self {
+        assert!(hz > 0, "rate must be at least 1 Hz");
+        Self { interval_ns: (Duration::from_secs(1).as_nanos() as u64 / hz).max(1), next_ns: 0, throttled: 0, clock }
+    }
+
+    /// Move to `clock`, starting over since its time base may differ.
+    pub(crate) fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+        self.next_ns = 0;
+    }
+
+    /// Sleep until the next call is due, then claim it. Returns true if it had to
+    /// sleep, i.e. the caller was running ahead of the rate.
+    pub fn pace(&mut self) -> bool {
+        let now = self.clock.now_nanos();
+        let throttled = now < self.next_ns;
+        if throttled {
+            self.clock.sleep(Duration::from_nanos(self.next_ns - now));
+            self.throttled += 1;
+        }
+        self.next_ns = self.next_ns.max(now) + self.interval_ns;
+        throttled
+    }
+
+    /// Gap between calls.
+    pub fn interval(&self) -> Duration {
+        Duration::from_nanos(self.interval_ns)
+    }
+
+    /// How many `pace` calls have slept so far.
+    pub fn throttled(&self) -> u64 {
+        self.throttled
+    }
+}
@@ -0,0 +1,128 @@
+//! Monitor handle over a segment mapped `PROT_READ`.
+
+use std::time::Duration;
+
+use crate::{
+    BusError, ExchangeStats, JournalHeader, Message, SizedBus, StatsSnapshot, SubscriptionMask, Topics, BITWORDS,
+    MAX_DATA_SIZE, MAX_SLOTS_PER_TOPIC, NUM_EXCHANGES, NUM_TOPICS,
+};
+
+/// A bus mapped read-only. Created by `Bus::open_readonly`; exposes only what can be
+/// done without storing to the segment, so a monitor can't clear pending bits, claim
+/// an ack row or bump the attach count. A buggy write through it faults instead of
+/// corrupting the bus.
+pub struct ReadOnlyBus<
+    const TOPICS: usize = NUM_TOPICS,
+    const SLOTS: usize = MAX_SLOTS_PER_TOPIC,
+    const DATA: usize = MAX_DATA_SIZE,
+    const WORDS: usize = BITWORDS,
+> {
+    bus: SizedBus<TOPICS, SLOTS, DATA, WORDS>,
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    ReadOnlyBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    pub(crate) fn new(bus: SizedBus<TOPICS, SLOTS, DATA, WORDS>) -> Self {
+        Self { bus }
+    }
+
+    #[inline]
+    pub fn read(&self, topic_id: usize, seq: u64) -> Option<Message> {
+        self.bus.read(topic_id, seq)
+    }
+
+    #[inline]
+    pub fn read_latest(&self, topic_id: usize) -> Option<Message> {
+        self.bus.read_latest(topic_id)
+    }
+
+    #[inline]
+    pub fn read_oldest(&self, topic_id: usize) -> Option<Message> {
+        self.bus.read_oldest(topic_id)
+    }
+
+    #[inline]
+    pub fn get_latest_seq(&self, topic_id: usize) -> u64 {
+        self.bus.get_latest_seq(topic_id)
+    }
+
+    /// `Bus::drain_all_resident`; only reads the rings.
+    pub fn drain_all_resident(&self, mask: &SubscriptionMask) -> Vec<(usize, Message)> {
+        self.bus.drain_all_resident(mask)
+    }
+
+    #[inline]
+    pub fn active_topics(&self) -> Topics {
+        self.bus.active_topics()
+    }
+
+    /// Pending bits as they stand; a monitor can't take them, only look.
+    #[inline]
+    pub fn pending_snapshot(&self) -> Vec<u64> {
+        self.bus.pending_snapshot()
+    }
+
+    /// `pending_snapshot` without allocating; see `Bus::pending_snapshot_into`.
+    #[inline]
+    pub fn pending_snapshot_into(&self, out: &mut [u64]) -> usize {
+        self.bus.pending_snapshot_into(out)
+    }
+
+    /// Block on the notification semaphore. The wake-up is consumed like any other
+    /// consumer's, so a monitor waiting on a bus competes with the real consumers.
+    #[inline]
+    pub fn wait(&self) {
+        self.bus.wait()
+    }
+
+    #[inline]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        self.bus.wait_timeout(timeout)
+    }
+
+    #[inline]
+    pub fn stats(&self) -> StatsSnapshot {
+        self.bus.stats()
+    }
+
+    #[inline]
+    pub fn stats_prometheus(&self) -> String {
+        self.bus.stats_prometheus()
+    }
+
+    #[inline]
+    pub fn stats_by_exchange(&self) -> [ExchangeStats; NUM_EXCHANGES] {
+        self.bus.stats_by_exchange()
+    }
+
+    #[inline]
+    pub fn header(&self) -> &JournalHeader {
+        self.bus.header()
+    }
+
+    #[inline]
+    pub fn is_shutting_down(&self) -> bool {
+        self.bus.is_shutting_down()
+    }
+
+    #[inline]
+    pub fn resolve_topic(&self, name: &str) -> Option<usize> {
+        self.bus.resolve_topic(name)
+    }
+
+    #[inline]
+    pub fn topic_name(&self, topic_id: usize) -> Option<String> {
+        self.bus.topic_name(topic_id)
+    }
+
+    /// `Bus::segment_replaced`.
+    pub fn segment_replaced(&self) -> bool {
+        self.bus.segment_replaced()
+    }
+
+    /// `Bus::reopen`, mapping the new segment read-only as well.
+    pub fn reopen(&mut self) -> Result<(), BusError> {
+        self.bus.reopen()
+    }
+}
@@ -0,0 +1,265 @@
+//! Capture bus traffic to a file and replay it onto another bus.
+//!
+//! File layout (all integers little-endian):
+//!
+//! ```text
+//! file header : b"IPCBREC4" | layout_version u32 | num_topics u32 | total_slots u32 | data_size u32
+//!               | channel_data_size u32 x NUM_CHANNELS | channel_slots u32 x NUM_CHANNELS
+//!               | payload_align u32 | coalesce u64 x BITWORDS
+//! record      : body_len u32 | timestamp_nanos u64 | topic_id u32 | seq u64 | msg_type u16 | flags u16 | data[body_len - 24]
+//! ```
+//!
+//! The recorded bus's journal dimensions and per-channel config are stored once up
+//! front so a `Player` can refuse to replay onto a bus with a different layout.
+//! Bit 0 of `flags` marks a compressed payload and the high byte holds the
+//! `schema_version`.
+
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use crate::{JournalHeader, Message, SizedBus, SubscriptionMask, BITWORDS, EMPTY_SEQ, NUM_CHANNELS};
+
+const FILE_MAGIC: [u8; 8] = *b"IPCBREC4";
+const RECORD_FIXED_LEN: usize = 8 + 4 + 8 + 2 + 2;
+const RECORD_COMPRESSED: u16 = 1 << 0;
+
+/// Journal dimensions and per-channel config of the bus a recording was captured from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecordingHeader {
+    pub layout_version: u32,
+    pub num_topics: u32,
+    pub total_slots: u32,
+    pub data_size: u32,
+    pub channel_data_size: [u32; NUM_CHANNELS],
+    pub channel_slots: [u32; NUM_CHANNELS],
+    pub payload_align: u32,
+    pub coalesce: [u64; BITWORDS],
+}
+
+impl RecordingHeader {
+    fn of(header: &JournalHeader) -> Self {
+        Self {
+            layout_version: header.layout_version,
+            num_topics: header.num_topics,
+            total_slots: header.total_slots,
+            data_size: header.data_size,
+            channel_data_size: header.channel_data_size,
+            channel_slots: header.channel_slots,
+            payload_align: header.payload_align,
+            coalesce: header.coalesce,
+        }
+    }
+
+    /// True if a bus with this journal header can take the recorded traffic: same
+    /// dimensions, slot sizes and depths per channel, alignment and coalesced topics.
+    pub fn matches(&self, header: &JournalHeader) -> bool {
+        self.num_topics == header.num_topics
+            && self.total_slots == header.total_slots
+            && self.data_size == header.data_size
+            && self.channel_data_size == header.channel_data_size
+            && self.channel_slots == header.channel_slots
+            && self.payload_align == header.payload_align
+            && self.coalesce == header.coalesce
+    }
+
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        for v in [self.layout_version, self.num_topics, self.total_slots, self.data_size] {
+            out.write_all(&v.to_le_bytes())?;
+        }
+        for v in self.channel_data_size.iter().chain(&self.channel_slots).chain([&self.payload_align]) {
+            out.write_all(&v.to_le_bytes())?;
+        }
+        self.coalesce.iter().try_for_each(|w| out.write_all(&w.to_le_bytes()))
+    }
+
+    fn read_from(input: &mut impl Read) -> io::Result<Self> {
+        let mut u32s = [0u32; 5 + 2 * NUM_CHANNELS];
+        for v in &mut u32s {
+            let mut b = [0u8; 4];
+            input.read_exact(&mut b)?;
+            *v = u32::from_le_bytes(b);
+        }
+        let mut coalesce = [0u64; BITWORDS];
+        for w in &mut coalesce {
+            let mut b = [0u8; 8];
+            input.read_exact(&mut b)?;
+            *w = u64::from_le_bytes(b);
+        }
+        let channels = &u32s[4..4 + 2 * NUM_CHANNELS];
+        Ok(Self {
+            layout_version: u32s[0],
+            num_topics: u32s[1],
+            total_slots: u32s[2],
+            data_size: u32s[3],
+            channel_data_size: channels[..NUM_CHANNELS].try_into().unwrap(),
+            channel_slots: channels[NUM_CHANNELS..].try_into().unwrap(),
+            payload_align: u32s[4 + 2 * NUM_CHANNELS],
+            coalesce,
+        })
+    }
+}
+
+/// One captured message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// The message's `publish_ts_nanos`.
+    pub timestamp_nanos: u64,
+    pub topic_id: usize,
+    pub seq: u64,
+    pub msg_type: u16,
+    pub schema_version: u8,
+    /// `data` is the compressed block the message was stored as.
+    pub compressed: bool,
+    pub data: Vec<u8>,
+}
+
+/// Appends drained messages to a recording.
+pub struct Recorder<W: Write> {
+    out: W,
+    /// Newest seq recorded per topic.
+    last_seen: Vec<u64>,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a recording of `bus`'s traffic, writing the file header.
+    pub fn new<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>(
+        mut out: W,
+        bus: &SizedBus<TOPICS, SLOTS, DATA, WORDS>,
+    ) -> io::Result<Self> {
+        out.write_all(&FILE_MAGIC)?;
+        RecordingHeader::of(bus.header()).write_to(&mut out)?;
+        Ok(Self { out, last_seen: vec![EMPTY_SEQ; bus.num_topics()] })
+    }
+
+    pub fn record(&mut self, topic_id: usize, msg: &Message) -> io::Result<()> {
+        let data = msg.payload();
+        let body_len = (RECORD_FIXED_LEN + data.len()) as u32;
+        self.out.write_all(&body_len.to_le_bytes())?;
+        self.out.write_all(&msg.publish_ts_nanos.to_le_bytes())?;
+        self.out.write_all(&(topic_id as u32).to_le_bytes())?;
+        self.out.write_all(&msg.seq.to_le_bytes())?;
+        self.out.write_all(&msg.msg_type.to_le_bytes())?;
+        let flags = if msg.compressed { RECORD_COMPRESSED } else { 0 } | (msg.schema_version as u16) << 8;
+        self.out.write_all(&flags.to_le_bytes())?;
+        self.out.write_all(data)
+    }
+
+    /// Drain the pending topics in `mask` and record every resident message of each
+    /// newer than the last one recorded, in seq order (the first drain of a topic
+    /// records its whole ring). Returns how many records were written.
+    pub fn record_drain<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>(
+        &mut self,
+        bus: &SizedBus<TOPICS, SLOTS, DATA, WORDS>,
+        mask: &SubscriptionMask,
+    ) -> io::Result<usize> {
+        let mut written = 0;
+        let mut result = Ok(());
+        bus.drain_pending_mask(mask, |t| {
+            for msg in bus.topic(t).read_since(self.last_seen[t]) {
+                if result.is_err() {
+                    return;
+                }
+                result = self.record(t, &msg);
+                self.last_seen[t] = msg.seq;
+                written += 1;
+            }
+        });
+        result.map(|()| written)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Reads a recording and republishes it.
+pub struct Player<R: Read> {
+    input: R,
+    header: RecordingHeader,
+}
+
+impl<R: Read> Player<R> {
+    /// Open a recording, reading and checking the file header.
+    pub fn new(mut input: R) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if magic != FILE_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not an ipcbus recording"));
+        }
+        let header = RecordingHeader::read_from(&mut input)?;
+        Ok(Self { input, header })
+    }
+
+    pub fn header(&self) -> &RecordingHeader {
+        &self.header
+    }
+
+    /// Next record, or None at a clean end of file.
+    pub fn next_record(&mut self) -> io::Result<Option<Record>> {
+        let mut len = [0u8; 4];
+        match self.input.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let body_len = u32::from_le_bytes(len) as usize;
+        if body_len < RECORD_FIXED_LEN || body_len - RECORD_FIXED_LEN > self.header.data_size as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "corrupt record length"));
+        }
+        let mut body = vec![0u8; body_len];
+        self.input.read_exact(&mut body)?;
+        let flags = u16::from_le_bytes(body[22..24].try_into().unwrap());
+        Ok(Some(Record {
+            timestamp_nanos: u64::from_le_bytes(body[0..8].try_into().unwrap()),
+            topic_id: u32::from_le_bytes(body[8..12].try_into().unwrap()) as usize,
+            seq: u64::from_le_bytes(body[12..20].try_into().unwrap()),
+            msg_type: u16::from_le_bytes(body[20..22].try_into().unwrap()),
+            schema_version: (flags >> 8) as u8,
+            compressed: flags & RECORD_COMPRESSED != 0,
+            data: body.split_off(RECORD_FIXED_LEN),
+        }))
+    }
+
+    /// Write and notify every remaining record onto `bus`, or nothing if its layout
+    /// doesn't `match` the recording's. With `paced`, sleeps to
+    /// reproduce the original gaps between records on the bus's clock (so a bus on a
+    /// `MockClock` replays paced but instantly). Returns how many were replayed.
+    pub fn replay<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>(
+        &mut self,
+        bus: &SizedBus<TOPICS, SLOTS, DATA, WORDS>,
+        paced: bool,
+    ) -> io::Result<u64> {
+        if !self.header.matches(bus.header()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("recording layout {:?} doesn't match the target bus", self.header),
+            ));
+        }
+        let mut replayed = 0;
+        let mut prev_ts = None;
+        while let Some(rec) = self.next_record()? {
+            if rec.topic_id >= bus.num_topics() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "record topic out of range"));
+            }
+            if paced && let Some(prev) = prev_ts {
+                bus.clock().sleep(Duration::from_nanos(rec.timestamp_nanos.saturating_sub(prev)));
+            }
+            prev_ts = Some(rec.timestamp_nanos);
+            let msg = Message {
+                msg_type: rec.msg_type,
+                schema_version: rec.schema_version,
+                compressed: rec.compressed,
+                ..Message::with_payload(rec.seq, &rec.data, rec.timestamp_nanos)
+            };
+            bus.write_message(rec.topic_id, msg)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            bus.notify(rec.topic_id);
+            replayed += 1;
+        }
+        Ok(replayed)
+    }
+}
@@ -0,0 +1,169 @@
+//! Topic names shared through the segment, so producers and consumers agree on ids
+//! without an out-of-band table.
+//!
+//! The registry is an open-addressing hash table with one entry per topic id: a
+//! name hashes to a starting entry and probes linearly, and the index of the entry
+//! it ends up in *is* its topic id. An entry is claimed by CAS (`EMPTY -> CLAIMING`),
+//! filled in, then published (`READY`, Release). Names are never removed, so a probe
+//! that reaches an empty entry knows the name isn't registered. Two processes
+//! registering the same name follow the same probe sequence; the loser of the CAS
+//! waits for the winner's entry and finds its own name there.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{as_atomic, as_atomic_u32, BusError, SizedBus, NUM_TOPICS};
+
+/// Longest topic name, in bytes.
+pub const MAX_TOPIC_NAME_LEN: usize = 64;
+
+const NAME_WORDS: usize = MAX_TOPIC_NAME_LEN / 8;
+
+const EMPTY: u32 = 0;
+const CLAIMING: u32 = 1;
+const READY: u32 = 2;
+
+/// A claim not published within this long belongs to a process that died mid-store.
+/// `resolve_topic` probes past it; `register_topic` gives up with `RegistryBusy`,
+/// since registering further along could give the claimed name a second id.
+const CLAIM_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[repr(C)]
+pub struct RegistryEntry {
+    pub state: u32,
+    pub len: u32,
+    pub hash: u64,
+    pub name: [u64; NAME_WORDS],
+}
+
+/// Name table in the journal; entry `i` names topic `i`.
+#[repr(C)]
+pub struct Registry<const TOPICS: usize = NUM_TOPICS> {
+    pub entries: [RegistryEntry; TOPICS],
+}
+
+/// FNV-1a; only needs to spread names over the table.
+fn hash_name(name: &[u8]) -> u64 {
+    name.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3))
+}
+
+fn name_words(name: &[u8]) -> [u64; NAME_WORDS] {
+    let mut bytes = [0u8; MAX_TOPIC_NAME_LEN];
+    bytes[..name.len()].copy_from_slice(name);
+    core::array::from_fn(|i| u64::from_ne_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap()))
+}
+
+impl RegistryEntry {
+    #[inline]
+    fn state(&self) -> &AtomicU32 {
+        unsafe { as_atomic_u32(&self.state) }
+    }
+
+    /// The entry's state once it is no longer mid-claim (`CLAIMING` if the claim is
+    /// stuck).
+    fn settled_state(&self) -> u32 {
+        let mut deadline = None;
+        loop {
+            let state = self.state().load(Ordering::Acquire);
+            if state != CLAIMING {
+                return state;
+            }
+            let deadline = *deadline.get_or_insert_with(|| Instant::now() + CLAIM_TIMEOUT);
+            if Instant::now() >= deadline {
+                return CLAIMING;
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    /// True if this published entry holds `name` (with its `hash` and `words`).
+    fn holds(&self, hash: u64, len: usize, words: &[u64; NAME_WORDS]) -> bool {
+        unsafe { as_atomic(&self.hash) }.load(Ordering::Relaxed) == hash
+            && unsafe { as_atomic_u32(&self.len) }.load(Ordering::Relaxed) as usize == len
+            && self.name.iter().zip(words).all(|(w, &want)| unsafe { as_atomic(w) }.load(Ordering::Relaxed) == want)
+    }
+
+    fn name(&self) -> String {
+        let len = (unsafe { as_atomic_u32(&self.len) }.load(Ordering::Relaxed) as usize).min(MAX_TOPIC_NAME_LEN);
+        let bytes: Vec<u8> =
+            self.name.iter().flat_map(|w| unsafe { as_atomic(w) }.load(Ordering::Relaxed).to_ne_bytes()).collect();
+        String::from_utf8_lossy(&bytes[..len]).into_owned()
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    #[inline]
+    fn registry(&self) -> &Registry<TOPICS> {
+        unsafe { &(*self.journal).registry }
+    }
+
+    /// Topic id for `name` (e.g. "binance/spot/BTCUSDT/trades"), registering it on
+    /// first use. Every process that registers or resolves the same name gets the
+    /// same id for the life of the segment. Ids come from the same space as
+    /// `topic_id(...)`, so a bus should use one scheme or the other per topic.
+    ///
+    /// Fails with `RegistryBusy` if the name's probe meets an entry whose claim has
+    /// gone unpublished for a second.
+    pub fn register_topic(&self, name: &str) -> Result<usize, BusError> {
+        let name = name.as_bytes();
+        if name.len() > MAX_TOPIC_NAME_LEN {
+            return Err(BusError::TopicNameTooLong { len: name.len(), max: MAX_TOPIC_NAME_LEN });
+        }
+        let (hash, words) = (hash_name(name), name_words(name));
+        for id in probe(hash, self.num_topics()) {
+            let entry = &self.registry().entries[id];
+            let claimed = entry.state().compare_exchange(EMPTY, CLAIMING, Ordering::Acquire, Ordering::Relaxed).is_ok();
+            if claimed {
+                unsafe { as_atomic(&entry.hash) }.store(hash, Ordering::Relaxed);
+                unsafe { as_atomic_u32(&entry.len) }.store(name.len() as u32, Ordering::Relaxed);
+                for (w, &word) in entry.name.iter().zip(&words) {
+                    unsafe { as_atomic(w) }.store(word, Ordering::Relaxed);
+                }
+                entry.state().store(READY, Ordering::Release);
+                return Ok(id);
+            }
+            match entry.settled_state() {
+                READY if entry.holds(hash, name.len(), &words) => return Ok(id),
+                CLAIMING => return Err(BusError::RegistryBusy { topic_id: id }),
+                _ => {}
+            }
+        }
+        Err(BusError::RegistryFull { max: self.num_topics() })
+    }
+
+    /// Topic id `name` was registered under, or None if nobody has registered it.
+    pub fn resolve_topic(&self, name: &str) -> Option<usize> {
+        let name = name.as_bytes();
+        if name.len() > MAX_TOPIC_NAME_LEN {
+            return None;
+        }
+        let (hash, words) = (hash_name(name), name_words(name));
+        for id in probe(hash, self.num_topics()) {
+            let entry = &self.registry().entries[id];
+            match entry.settled_state() {
+                EMPTY => return None,
+                READY if entry.holds(hash, name.len(), &words) => return Some(id),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Name registered for `topic_id`, if any (for monitors and logs).
+    pub fn topic_name(&self, topic_id: usize) -> Option<String> {
+        let entry = &self.registry().entries[topic_id];
+        (entry.state().load(Ordering::Acquire) == READY).then(|| entry.name())
+    }
+}
+
+/// Entries a name with `hash` visits on a bus of `num_topics` topics, in order: every
+/// id once, from its home entry.
+fn probe(hash: u64, num_topics: usize) -> impl Iterator<Item = usize> {
+    let home = (hash % num_topics as u64) as usize;
+    (0..num_topics).map(move |i| (home + i) % num_topics)
+}
+
+// Names are stored as whole u64 words.
+const _: () = assert!(MAX_TOPIC_NAME_LEN.is_multiple_of(8));
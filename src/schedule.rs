@@ -0,0 +1,38 @@
+use crate::SubscriptionMask;
+
+/// Subscription groups with priorities, for `Bus::drain_prioritized`.
+/// Higher `priority` drains first; groups with equal priority keep insertion order.
+#[derive(Clone, Debug, Default)]
+pub struct DrainSchedule {
+    groups: Vec<(u32, SubscriptionMask)>,
+}
+
+impl DrainSchedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a group. A topic in several groups is served by the highest one.
+    pub fn group(mut self, priority: u32, mask: SubscriptionMask) -> Self {
+        let at = self.groups.partition_point(|&(p, _)| p >= priority);
+        self.groups.insert(at, (priority, mask));
+        self
+    }
+
+    /// Masks in drain order (highest priority first).
+    pub fn masks(&self) -> impl Iterator<Item = &SubscriptionMask> {
+        self.groups.iter().map(|(_, m)| m)
+    }
+
+    pub(crate) fn mask(&self, i: usize) -> &SubscriptionMask {
+        &self.groups[i].1
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
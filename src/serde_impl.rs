@@ -0,0 +1,63 @@
+//! `Serialize`/`Deserialize` for `Message` (feature `serde`).
+//!
+//! Only `seq`, `publish_ts_nanos`, `msg_type`, `schema_version`, `compressed` and
+//! the `data[..data_len]` payload go on the wire; the padded tail of the slot
+//! buffer is never encoded, and the CRC is recomputed on the way back in.
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Message, MAX_DATA_SIZE};
+
+#[derive(Serialize)]
+#[serde(rename = "Message")]
+struct WireRef<'a> {
+    seq: u64,
+    publish_ts_nanos: u64,
+    msg_type: u16,
+    schema_version: u8,
+    compressed: bool,
+    data: &'a [u8],
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Message")]
+struct WireOwned {
+    seq: u64,
+    publish_ts_nanos: u64,
+    msg_type: u16,
+    /// Absent from messages encoded before the field existed.
+    #[serde(default)]
+    schema_version: u8,
+    compressed: bool,
+    data: Vec<u8>,
+}
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        WireRef {
+            seq: self.seq,
+            publish_ts_nanos: self.publish_ts_nanos,
+            msg_type: self.msg_type,
+            schema_version: self.schema_version,
+            compressed: self.compressed,
+            data: self.payload(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = WireOwned::deserialize(deserializer)?;
+        if wire.data.len() > MAX_DATA_SIZE {
+            return Err(D::Error::invalid_length(wire.data.len(), &"a payload of at most MAX_DATA_SIZE bytes"));
+        }
+        Ok(Message {
+            msg_type: wire.msg_type,
+            schema_version: wire.schema_version,
+            compressed: wire.compressed,
+            ..Message::with_payload(wire.seq, &wire.data, wire.publish_ts_nanos)
+        })
+    }
+}
@@ -0,0 +1,300 @@
+//! Ring entries in the slot arena and the seqlock protocol guarding them.
+//!
+//! Each slot is a `SlotHeader` immediately followed by the topic's payload
+//! capacity in bytes (see `layout`). The producer bumps `version` to odd,
+//! writes, then publishes the next even value with Release; readers copy
+//! between two Acquire loads of `version` and retry if it moved.
+//!
+//! Every field and payload word is accessed through a relaxed atomic, so a racing
+//! reader sees stale or torn *values* (which the version check rejects) but never
+//! races on plain memory; `Bus::begin_write` fills the data area through the same
+//! words. `notify` follows the even Release store, so a consumer whose Acquire
+//! drain sees the pending bit sees the whole message.
+//!
+//! Those are the orderings of the default `OrderingProfile`; every function here
+//! takes the handle's profile and uses its orderings in their place.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{as_atomic, as_atomic_u16, as_atomic_u32, as_atomic_u8, Message, MessageRef, OrderingProfile};
+
+/// How many times a reader retries a slot that is mid-write before giving up.
+pub const SEQLOCK_RETRIES: usize = 64;
+
+/// The writer kept the slot busy for a reader's whole attempt budget.
+pub(crate) struct Contended;
+
+/// Fixed-size part of a ring entry; `data_len` payload bytes follow it.
+#[repr(C)]
+pub struct SlotHeader {
+    /// Seqlock version: odd while the producer is mid-write, even when stable.
+    /// 0 means the slot has never been written.
+    pub version: u64,
+    pub seq: u64,
+    pub publish_ts_nanos: u64,
+    pub data_len: u32,
+    pub crc: u32,
+    /// Caller-defined event type from `write_typed` (0 for a plain `write`).
+    pub msg_type: u16,
+    /// `SLOT_COMPRESSED` if the payload is LZ4-compressed (see `write_compressed`).
+    pub flags: u16,
+    /// From `write_versioned` (0 otherwise). Taken from what used to be reserved
+    /// bytes, which older builds left zeroed, so their messages read as version 0.
+    pub schema_version: u8,
+    _reserved: [u8; 3],
+}
+
+/// Every `SlotHeader` field but the version, as `store_header` writes them. The
+/// default is an empty slot (`EMPTY_SEQ`, no payload).
+#[derive(Clone, Copy, Default)]
+pub(crate) struct HeaderFields {
+    pub seq: u64,
+    pub publish_ts_nanos: u64,
+    pub data_len: u32,
+    pub crc: u32,
+    pub msg_type: u16,
+    pub schema_version: u8,
+    pub flags: u16,
+}
+
+/// `SlotHeader::flags` bit: the payload is a compressed block.
+pub(crate) const SLOT_COMPRESSED: u16 = 1 << 0;
+
+pub const SLOT_HEADER_SIZE: usize = core::mem::size_of::<SlotHeader>();
+
+#[inline]
+unsafe fn data_ptr(slot: *const SlotHeader) -> *mut u8 {
+    unsafe { (slot as *mut u8).add(SLOT_HEADER_SIZE) }
+}
+
+/// Payload word `i` of the slot. The data area is 8-aligned and its stride is a
+/// multiple of 8, so the word holding the last payload byte is still in the slot.
+#[inline]
+unsafe fn data_word<'a>(slot: *const SlotHeader, i: usize) -> &'a AtomicU64 {
+    unsafe { as_atomic((data_ptr(slot) as *const u64).add(i)) }
+}
+
+/// Store `payload` word by word, zero-padding the final partial word.
+#[inline]
+pub(crate) unsafe fn store_payload(slot: *const SlotHeader, payload: &[u8]) {
+    let mut chunks = payload.chunks_exact(8);
+    let mut i = 0;
+    for chunk in &mut chunks {
+        let word = u64::from_ne_bytes(chunk.try_into().unwrap());
+        unsafe { data_word(slot, i) }.store(word, Ordering::Relaxed);
+        i += 1;
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        let mut bytes = [0u8; 8];
+        bytes[..tail.len()].copy_from_slice(tail);
+        unsafe { data_word(slot, i) }.store(u64::from_ne_bytes(bytes), Ordering::Relaxed);
+    }
+}
+
+/// Store `bytes` at `offset` into the data area, merging them into the words they
+/// share with the bytes around them. For the writer holding the slot odd.
+pub(crate) unsafe fn store_bytes(slot: *const SlotHeader, offset: usize, bytes: &[u8]) {
+    let (mut pos, mut rest) = (offset, bytes);
+    while !rest.is_empty() {
+        let (at, n) = (pos % 8, rest.len().min(8 - pos % 8));
+        let word = unsafe { data_word(slot, pos / 8) };
+        let mut merged = word.load(Ordering::Relaxed).to_ne_bytes();
+        merged[at..at + n].copy_from_slice(&rest[..n]);
+        word.store(u64::from_ne_bytes(merged), Ordering::Relaxed);
+        (pos, rest) = (pos + n, &rest[n..]);
+    }
+}
+
+/// Load the first `out.len()` payload bytes into `out`.
+#[inline]
+pub(crate) unsafe fn load_payload(slot: *const SlotHeader, out: &mut [u8]) {
+    for (i, chunk) in out.chunks_mut(8).enumerate() {
+        let word = unsafe { data_word(slot, i) }.load(Ordering::Relaxed).to_ne_bytes();
+        chunk.copy_from_slice(&word[..chunk.len()]);
+    }
+}
+
+#[inline]
+fn flags_of(compressed: bool) -> u16 {
+    if compressed { SLOT_COMPRESSED } else { 0 }
+}
+
+/// Seq currently in `slot` (`EMPTY_SEQ` if never written or cleared), without the
+/// seqlock. Exact for the topic's producer, the slot's sole writer; anyone else may
+/// see the seq of a store still in progress.
+#[inline]
+pub(crate) unsafe fn resident_seq(slot: *const SlotHeader) -> u64 {
+    unsafe { as_atomic(&raw const (*slot).seq) }.load(Ordering::Relaxed)
+}
+
+/// Seqlock-publish `msg` into `slot`. The payload must fit the slot's capacity.
+#[inline]
+pub(crate) unsafe fn store(slot: *mut SlotHeader, msg: &Message, ord: OrderingProfile) {
+    unsafe {
+        // Seqlock write: go odd, store the payload, then publish the even version.
+        let odd = begin(slot, ord);
+        let payload = msg.payload();
+        let fields = HeaderFields {
+            seq: msg.seq,
+            publish_ts_nanos: msg.publish_ts_nanos,
+            data_len: payload.len() as u32,
+            crc: msg.crc,
+            msg_type: msg.msg_type,
+            schema_version: msg.schema_version,
+            flags: flags_of(msg.compressed),
+        };
+        store_header(slot, &fields);
+        store_payload(slot, payload);
+        publish(slot, odd, ord);
+    }
+}
+
+/// Take `slot` odd for a write; returns the odd version to `publish` when done.
+#[inline]
+pub(crate) unsafe fn begin(slot: *mut SlotHeader, ord: OrderingProfile) -> u64 {
+    let version = unsafe { as_atomic(&raw const (*slot).version) };
+    let odd = version.load(Ordering::Relaxed) | 1;
+    version.store(odd, Ordering::Relaxed);
+    ord.fence_release();
+    odd
+}
+
+/// Store every header field but the version.
+#[inline]
+pub(crate) unsafe fn store_header(slot: *mut SlotHeader, fields: &HeaderFields) {
+    unsafe {
+        as_atomic(&raw const (*slot).seq).store(fields.seq, Ordering::Relaxed);
+        as_atomic(&raw const (*slot).publish_ts_nanos).store(fields.publish_ts_nanos, Ordering::Relaxed);
+        as_atomic_u32(&raw const (*slot).data_len).store(fields.data_len, Ordering::Relaxed);
+        as_atomic_u32(&raw const (*slot).crc).store(fields.crc, Ordering::Relaxed);
+        as_atomic_u16(&raw const (*slot).msg_type).store(fields.msg_type, Ordering::Relaxed);
+        as_atomic_u16(&raw const (*slot).flags).store(fields.flags, Ordering::Relaxed);
+        as_atomic_u8(&raw const (*slot).schema_version).store(fields.schema_version, Ordering::Relaxed);
+    }
+}
+
+/// End the write `begin` returned `odd` for: everything stored since happens-before
+/// any Acquire load that sees the even version.
+#[inline]
+pub(crate) unsafe fn publish(slot: *mut SlotHeader, odd: u64, ord: OrderingProfile) {
+    unsafe { as_atomic(&raw const (*slot).version) }.store(odd.wrapping_add(1), ord.release());
+}
+
+/// Reset `slot` to hold `EMPTY_SEQ` with no payload. The version keeps counting up
+/// rather than returning to 0, so a reader that started before the clear can't
+/// mistake a later write for the version it first saw.
+#[inline]
+pub(crate) unsafe fn clear(slot: *mut SlotHeader, ord: OrderingProfile) {
+    unsafe {
+        if as_atomic(&raw const (*slot).version).load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let odd = begin(slot, ord);
+        store_header(slot, &HeaderFields::default());
+        publish(slot, odd, ord);
+    }
+}
+
+/// Consistent copy of a slot, or None if it was never written
+/// (or stayed mid-write for the whole retry budget).
+#[inline]
+pub(crate) unsafe fn load(slot: *const SlotHeader, capacity: usize, ord: OrderingProfile) -> Option<Message> {
+    unsafe { load_within(slot, capacity, SEQLOCK_RETRIES, ord) }.ok().and_then(|(msg, _)| msg)
+}
+
+/// `load` with an explicit attempt budget, telling a never-written slot (`Ok(None)`)
+/// apart from one that stayed busy (`Err(Contended)`). A stable read also reports how
+/// many attempts failed before it.
+#[inline]
+pub(crate) unsafe fn load_within(
+    slot: *const SlotHeader,
+    capacity: usize,
+    attempts: usize,
+    ord: OrderingProfile,
+) -> Result<(Option<Message>, usize), Contended> {
+    let mut msg = Message::default();
+    unsafe { load_into(slot, capacity, attempts, ord, &mut msg) }.map(|(written, retries)| (written.then_some(msg), retries))
+}
+
+/// `load_within` into `out`, copying only the stored payload bytes: `out.data` past
+/// `data_len` keeps whatever it held. `Ok((false, _))` if the slot was never written.
+#[inline]
+pub(crate) unsafe fn load_into(
+    slot: *const SlotHeader,
+    capacity: usize,
+    attempts: usize,
+    ord: OrderingProfile,
+    out: &mut Message,
+) -> Result<(bool, usize), Contended> {
+    unsafe {
+        let version = as_atomic(&raw const (*slot).version);
+
+        for retries in 0..attempts {
+            let before = version.load(ord.acquire());
+            if before == 0 {
+                return Ok((false, retries));
+            }
+            if before & 1 == 1 {
+                core::hint::spin_loop();
+                continue;
+            }
+            out.seq = as_atomic(&raw const (*slot).seq).load(Ordering::Relaxed);
+            out.publish_ts_nanos = as_atomic(&raw const (*slot).publish_ts_nanos).load(Ordering::Relaxed);
+            out.crc = as_atomic_u32(&raw const (*slot).crc).load(Ordering::Relaxed);
+            out.msg_type = as_atomic_u16(&raw const (*slot).msg_type).load(Ordering::Relaxed);
+            out.schema_version = as_atomic_u8(&raw const (*slot).schema_version).load(Ordering::Relaxed);
+            out.compressed = as_atomic_u16(&raw const (*slot).flags).load(Ordering::Relaxed) & SLOT_COMPRESSED != 0;
+            // Any process mapping the segment can store a bogus length; never let it
+            // reach past the slot. The CRC, taken over the real payload, then fails.
+            let len = (as_atomic_u32(&raw const (*slot).data_len).load(Ordering::Relaxed) as usize).min(capacity);
+            out.data_len = len as u32;
+            load_payload(slot, &mut out.data[..len]);
+            ord.fence_acquire();
+            if version.load(Ordering::Relaxed) == before {
+                return Ok((true, retries));
+            }
+        }
+        Err(Contended)
+    }
+}
+
+/// Borrow the payload of `slot` in place if it currently holds `seq`.
+#[inline]
+pub(crate) unsafe fn load_ref<'a>(
+    slot: *const SlotHeader,
+    capacity: usize,
+    seq: u64,
+    ord: OrderingProfile,
+) -> Option<MessageRef<'a>> {
+    unsafe {
+        let version = as_atomic(&raw const (*slot).version);
+
+        for _ in 0..SEQLOCK_RETRIES {
+            let before = version.load(ord.acquire());
+            if before == 0 {
+                return None;
+            }
+            if before & 1 == 1 {
+                core::hint::spin_loop();
+                continue;
+            }
+            let stored_seq = as_atomic(&raw const (*slot).seq).load(Ordering::Relaxed);
+            let publish_ts_nanos = as_atomic(&raw const (*slot).publish_ts_nanos).load(Ordering::Relaxed);
+            let len = (as_atomic_u32(&raw const (*slot).data_len).load(Ordering::Relaxed) as usize).min(capacity);
+            let msg_type = as_atomic_u16(&raw const (*slot).msg_type).load(Ordering::Relaxed);
+            let schema_version = as_atomic_u8(&raw const (*slot).schema_version).load(Ordering::Relaxed);
+            let compressed = as_atomic_u16(&raw const (*slot).flags).load(Ordering::Relaxed) & SLOT_COMPRESSED != 0;
+            ord.fence_acquire();
+            if version.load(Ordering::Relaxed) != before {
+                continue;
+            }
+            if stored_seq != seq {
+                return None;
+            }
+            let data = core::slice::from_raw_parts(data_ptr(slot), len);
+            return Some(MessageRef { seq, publish_ts_nanos, msg_type, schema_version, compressed, data, version, observed: before });
+        }
+        None
+    }
+}
@@ -0,0 +1,138 @@
+use core::fmt::Write;
+use core::sync::atomic::Ordering;
+
+use crate::{as_atomic, Bus, NUM_EXCHANGES, NUM_GROUPS, NUM_TOPICS};
+
+/// Counters living in the journal, bumped with relaxed increments on the hot path.
+/// Any process attached to the bus can read them through `Bus::stats()`.
+#[repr(C)]
+pub struct Stats<const TOPICS: usize = NUM_TOPICS> {
+    pub topics: [TopicStats; TOPICS],
+    /// Indexed by notification group, so consumers of different groups never
+    /// contend for a line either.
+    pub groups: [GroupStats; NUM_GROUPS],
+}
+
+/// The words one topic's producer writes on every message, on a cache line of their
+/// own so producers of adjacent topics never contend for a line.
+#[repr(C, align(64))]
+pub struct TopicStats {
+    /// Messages stored.
+    pub writes: u64,
+    /// `notify` calls, batched ones included.
+    pub notifies: u64,
+    /// Unacked messages overwritten by `write_with_overflow`.
+    pub overflows: u64,
+    /// Bus-clock time of the last write or `heartbeat`; 0 if never. Not a counter,
+    /// but stored alongside `writes`, so it shares the line.
+    pub last_heartbeat_ns: u64,
+    /// Notifies that skipped `sem_post` because the topic was already pending (a
+    /// `write_batch` counts once, against its first topic).
+    pub posts_suppressed: u64,
+}
+
+/// The words a group's consumers bump while draining, on a cache line of their own.
+#[repr(C, align(64))]
+pub struct GroupStats {
+    /// Topics handed to a drain callback.
+    pub drain_events: u64,
+}
+
+impl Stats {
+    #[inline]
+    pub(crate) fn bump(counter: &u64) {
+        unsafe { as_atomic(counter) }.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl<const TOPICS: usize> Stats<TOPICS> {
+    pub(crate) fn snapshot(&self) -> StatsSnapshot {
+        let load = |c: &u64| unsafe { as_atomic(c) }.load(Ordering::Relaxed);
+        StatsSnapshot {
+            writes: core::array::from_fn(|t| self.topics.get(t).map_or(0, |s| load(&s.writes))),
+            notifies: core::array::from_fn(|t| self.topics.get(t).map_or(0, |s| load(&s.notifies))),
+            overflows: core::array::from_fn(|t| self.topics.get(t).map_or(0, |s| load(&s.overflows))),
+            posts_suppressed: self.topics.iter().map(|t| load(&t.posts_suppressed)).sum(),
+            drain_events: self.groups.iter().map(|g| load(&g.drain_events)).sum(),
+        }
+    }
+}
+
+/// One exchange's share of a `StatsSnapshot`: the sums over its topics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExchangeStats {
+    pub writes: u64,
+    pub notifies: u64,
+    /// Unacked messages overwritten (dropped) by `write_with_overflow`.
+    pub overflows: u64,
+}
+
+/// Point-in-time copy of the journal's `Stats`. Counters are read individually,
+/// so the snapshot is not atomic across fields.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    pub writes: [u64; NUM_TOPICS],
+    pub notifies: [u64; NUM_TOPICS],
+    pub overflows: [u64; NUM_TOPICS],
+    /// Summed over every topic.
+    pub posts_suppressed: u64,
+    /// Summed over every group.
+    pub drain_events: u64,
+}
+
+impl StatsSnapshot {
+    pub fn total_writes(&self) -> u64 {
+        self.writes.iter().sum()
+    }
+
+    pub fn total_notifies(&self) -> u64 {
+        self.notifies.iter().sum()
+    }
+
+    pub fn total_overflows(&self) -> u64 {
+        self.overflows.iter().sum()
+    }
+
+    /// Per-topic counters rolled up by the exchange coordinate of each topic.
+    pub fn by_exchange(&self) -> [ExchangeStats; NUM_EXCHANGES] {
+        let mut out = [ExchangeStats::default(); NUM_EXCHANGES];
+        for t in 0..NUM_TOPICS {
+            let e = &mut out[Bus::topic_coords(t).0];
+            e.writes += self.writes[t];
+            e.notifies += self.notifies[t];
+            e.overflows += self.overflows[t];
+        }
+        out
+    }
+
+    /// The counters in Prometheus text exposition format. Per-topic series carry
+    /// `topic` plus its `exchange`/`market`/`symbol`/`channel` coordinates, and are
+    /// left out for topics whose counters are all still 0.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let per_topic: [(&str, &str, &[u64; NUM_TOPICS]); 3] = [
+            ("ipcbus_writes_total", "Messages stored.", &self.writes),
+            ("ipcbus_notifies_total", "notify calls, batched ones included.", &self.notifies),
+            ("ipcbus_overflows_total", "Unacked messages overwritten by write_with_overflow.", &self.overflows),
+        ];
+        for (metric, help, counts) in per_topic {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter", metric, help, metric);
+            for t in (0..NUM_TOPICS).filter(|&t| self.writes[t] | self.notifies[t] | self.overflows[t] != 0) {
+                let (e, m, s, c) = Bus::topic_coords(t);
+                let _ = writeln!(
+                    out,
+                    "{}{{topic=\"{}\",exchange=\"{}\",market=\"{}\",symbol=\"{}\",channel=\"{}\"}} {}",
+                    metric, t, e, m, s, c, counts[t]
+                );
+            }
+        }
+        let bus_wide = [
+            ("ipcbus_posts_suppressed_total", "Notifies that found the topic already pending.", self.posts_suppressed),
+            ("ipcbus_drain_events_total", "Topics handed to a drain callback.", self.drain_events),
+        ];
+        for (metric, help, count) in bus_wide {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter\n{} {}", metric, help, metric, metric, count);
+        }
+        out
+    }
+}
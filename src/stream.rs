@@ -0,0 +1,159 @@
+//! Async consumer side for tokio runtimes (feature `tokio`).
+//!
+//! Named semaphores can't be registered with the reactor, so the wait runs on
+//! tokio's blocking pool and the async task just awaits its completion; other
+//! tasks keep running while the consumer is idle.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+
+use futures_core::Stream;
+
+use crate::notify::Notifier;
+use crate::{Message, SizedBus, SubscriptionMask, BITWORDS, MAX_DATA_SIZE, MAX_SLOTS_PER_TOPIC, NUM_TOPICS};
+
+const WAITING: u8 = 0;
+const WOKEN: u8 = 1;
+const CANCELLED: u8 = 2;
+
+/// How often a parked blocking waiter checks whether its future was dropped.
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+/// Marks a pending wait as cancelled when its future is dropped early. If the
+/// blocking side already took a token nobody will act on, that token is posted
+/// back so the wakeup isn't lost.
+struct CancelOnDrop {
+    state: Arc<AtomicU8>,
+    notifier: Arc<dyn Notifier>,
+    armed: bool,
+}
+
+impl CancelOnDrop {
+    /// The wait completed and its caller saw it; nothing to undo.
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        if let Err(WOKEN) = self.state.compare_exchange(WAITING, CANCELLED, Ordering::AcqRel, Ordering::Acquire) {
+            self.notifier.post();
+        }
+    }
+}
+
+/// Wait for a notification on the blocking pool. The parked thread re-checks for
+/// cancellation every CANCEL_POLL, so dropping the future never strands a thread
+/// in `sem_wait` (which would also stall runtime shutdown). With a `window` (the
+/// segment's post window) it also resolves once that passes without a post, since
+/// the post for bits already pending may be held back; the caller re-checks them.
+fn wait_blocking(notifier: Arc<dyn Notifier>, window: Option<Duration>) -> impl Future<Output = ()> + Send + 'static {
+    let state = Arc::new(AtomicU8::new(WAITING));
+    let guard = CancelOnDrop { state: state.clone(), notifier: notifier.clone(), armed: true };
+    async move {
+        let _ = tokio::task::spawn_blocking(move || {
+            let poll = window.map_or(CANCEL_POLL, |w| w.min(CANCEL_POLL));
+            let mut waited = Duration::ZERO;
+            loop {
+                if notifier.wait_timeout(poll) {
+                    if state.compare_exchange(WAITING, WOKEN, Ordering::AcqRel, Ordering::Acquire).is_err() {
+                        // The future is gone; hand the token back.
+                        notifier.post();
+                    }
+                    return;
+                }
+                if state.load(Ordering::Acquire) == CANCELLED {
+                    return;
+                }
+                waited += poll;
+                if window.is_some_and(|w| waited >= w) {
+                    return;
+                }
+            }
+        })
+        .await;
+        guard.disarm();
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Async counterpart of `wait`: resolves when any topic is notified,
+    /// without blocking the executor thread.
+    pub async fn wait_async(&self) {
+        wait_blocking(self.notifier.clone(), self.header().post_window()).await
+    }
+
+    /// Stream of drained `(topic_id, latest message)` pairs for the topics in `mask`.
+    pub fn stream(self: Arc<Self>, mask: SubscriptionMask) -> BusStream<TOPICS, SLOTS, DATA, WORDS> {
+        BusStream { bus: self, mask, ready: VecDeque::new(), wait: None }
+    }
+}
+
+/// Yields the latest resident message of each pending subscribed topic, waking
+/// on bus notifications. Created by `Bus::stream`; ends once the bus is shutting
+/// down and everything already pending has been yielded.
+pub struct BusStream<
+    const TOPICS: usize = NUM_TOPICS,
+    const SLOTS: usize = MAX_SLOTS_PER_TOPIC,
+    const DATA: usize = MAX_DATA_SIZE,
+    const WORDS: usize = BITWORDS,
+> {
+    bus: Arc<SizedBus<TOPICS, SLOTS, DATA, WORDS>>,
+    mask: SubscriptionMask,
+    ready: VecDeque<(usize, Message)>,
+    wait: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    BusStream<TOPICS, SLOTS, DATA, WORDS>
+{
+    fn collect(&mut self) {
+        let (bus, ready) = (&self.bus, &mut self.ready);
+        let mut on_msg = |t: usize, msg: Message| ready.push_back((t, msg));
+        bus.drain_pending_with(&self.mask, &mut on_msg);
+        while bus.try_wait() {
+            bus.drain_pending_with(&self.mask, &mut on_msg);
+        }
+    }
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize> Stream
+    for BusStream<TOPICS, SLOTS, DATA, WORDS>
+{
+    type Item = (usize, Message);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(ev) = this.ready.pop_front() {
+                return Poll::Ready(Some(ev));
+            }
+            if this.bus.is_shutting_down() {
+                this.collect();
+                if this.ready.is_empty() {
+                    return Poll::Ready(None);
+                }
+                continue;
+            }
+            let window = this.bus.header().post_window();
+            let wait = this.wait.get_or_insert_with(|| Box::pin(wait_blocking(this.bus.notifier.clone(), window)));
+            if wait.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            this.wait = None;
+            this.bus.relay_shutdown();
+            this.collect();
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Typed handle for one topic, so call sites stop threading raw ids around.
+
+use core::cmp::Ordering;
+
+use crate::layout::TopicLayout;
+use crate::{
+    seq_newer, BusError, Message, SizedBus, BITWORDS, EMPTY_SEQ, MAX_DATA_SIZE, MAX_SLOTS_PER_TOPIC, NUM_TOPICS,
+};
+
+/// One topic of a bus, with its ring location looked up once. Created by `Bus::topic`.
+#[derive(Clone, Copy)]
+pub struct Topic<
+    'a,
+    const TOPICS: usize = NUM_TOPICS,
+    const SLOTS: usize = MAX_SLOTS_PER_TOPIC,
+    const DATA: usize = MAX_DATA_SIZE,
+    const WORDS: usize = BITWORDS,
+> {
+    bus: &'a SizedBus<TOPICS, SLOTS, DATA, WORDS>,
+    id: usize,
+    layout: TopicLayout,
+}
+
+impl<const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    SizedBus<TOPICS, SLOTS, DATA, WORDS>
+{
+    /// Handle for `topic_id`. Panics if it is out of range.
+    #[inline]
+    pub fn topic(&self, topic_id: usize) -> Topic<'_, TOPICS, SLOTS, DATA, WORDS> {
+        assert!(topic_id < self.num_topics(), "topic_id {} out of range", topic_id);
+        Topic { bus: self, id: topic_id, layout: self.layout.topics[topic_id] }
+    }
+}
+
+impl<'a, const TOPICS: usize, const SLOTS: usize, const DATA: usize, const WORDS: usize>
+    Topic<'a, TOPICS, SLOTS, DATA, WORDS>
+{
+    #[inline]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Payload bytes a slot of this topic can hold.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.layout.capacity
+    }
+
+    /// `Bus::write` for this topic.
+    #[inline]
+    pub fn write(&self, seq: u64, data: &[u8]) -> Result<(), BusError> {
+        crate::check_seq(seq)?;
+        if data.len() > self.layout.capacity {
+            return Err(BusError::PayloadTooLarge { len: data.len(), max: self.layout.capacity });
+        }
+        self.bus.store_in(self.id, &self.layout, Message::with_payload(seq, data, self.bus.now()));
+        Ok(())
+    }
+
+    /// `Bus::notify` for this topic.
+    #[inline]
+    pub fn notify(&self) {
+        self.bus.notify(self.id);
+    }
+
+    /// `Bus::read` for this topic.
+    #[inline]
+    pub fn read(&self, seq: u64) -> Option<Message> {
+        self.bus.read_in(&self.layout, seq)
+    }
+
+    /// `Bus::read_latest` for this topic.
+    #[inline]
+    pub fn latest(&self) -> Option<Message> {
+        self.bus.latest_in(&self.layout)
+    }
+
+    /// `Bus::read_oldest` for this topic.
+    #[inline]
+    pub fn oldest(&self) -> Option<Message> {
+        self.bus.oldest_in(&self.layout)
+    }
+
+    /// Resident messages newer than `after` (by `seq_newer`; `EMPTY_SEQ` means
+    /// everything), oldest first. Anything already overwritten in the ring is simply absent.
+    pub fn read_since(&self, after: u64) -> Vec<Message> {
+        let mut msgs = Vec::new();
+        self.bus.for_each_resident(&self.layout, |msg| {
+            if after == EMPTY_SEQ || seq_newer(msg.seq, after) {
+                msgs.push(msg);
+            }
+        });
+        // A consistent order as long as the resident seqs span less than 2^63.
+        msgs.sort_unstable_by(|a, b| match (a.seq == b.seq, seq_newer(a.seq, b.seq)) {
+            (true, _) => Ordering::Equal,
+            (false, true) => Ordering::Greater,
+            (false, false) => Ordering::Less,
+        });
+        msgs
+    }
+}
@@ -0,0 +1,81 @@
+//! Producer-side post coalescing (`BusConfig::post_window`).
+//!
+//! With a bursty producer and a slow consumer, a topic's pending bit goes 0 -> 1
+//! again right after every drain, and every such transition posts the semaphore.
+//! With a window, a handle posts each group at most once per window: a transition
+//! inside it only marks the group's post as held, and held posts go out on the
+//! first `notify` after the window ends, on `Bus::flush_posts`, or when the handle
+//! drops. The pending bits are set either way, so whatever wakes the consumer
+//! drains them all. A producer that goes quiet may hold a post indefinitely, so
+//! the handle also records its window in the header, and consumers wait at most
+//! that long before checking the bits themselves.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+use crate::NUM_GROUPS;
+
+/// `last` of a group this handle never posted.
+const NEVER: u64 = u64::MAX;
+
+pub(crate) struct PostWindow {
+    nanos: u64,
+    /// When this handle last posted each group.
+    last: [AtomicU64; NUM_GROUPS],
+    /// Bit `g` is set while a post to group `g` is held back.
+    held: AtomicU64,
+}
+
+impl PostWindow {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            nanos: window.as_nanos().min(u64::MAX as u128) as u64,
+            last: core::array::from_fn(|_| AtomicU64::new(NEVER)),
+            held: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether group `g` may be posted now; if not, the post is held. `now` is only
+    /// read with a window set.
+    #[inline]
+    pub(crate) fn admit(&self, g: usize, now: impl FnOnce() -> u64) -> bool {
+        if self.nanos == 0 {
+            return true;
+        }
+        if self.claim(g, now()) {
+            return true;
+        }
+        self.held.fetch_or(1 << g, Ordering::Relaxed);
+        false
+    }
+
+    /// Groups whose held post is due (with `force`, every held one), no longer held.
+    #[inline]
+    pub(crate) fn take_due(&self, force: bool, now: impl FnOnce() -> u64) -> u64 {
+        let held = self.held.load(Ordering::Relaxed);
+        if held == 0 {
+            return 0;
+        }
+        let now = now();
+        let mut due = 0;
+        let mut left = held;
+        while left != 0 {
+            let g = left.trailing_zeros() as usize;
+            if force {
+                self.last[g].store(now, Ordering::Relaxed);
+                due |= 1 << g;
+            } else if self.claim(g, now) {
+                due |= 1 << g;
+            }
+            left &= left - 1;
+        }
+        self.held.fetch_and(!due, Ordering::Relaxed) & due
+    }
+
+    /// Start a new window for group `g` at `now` if the last one is over.
+    fn claim(&self, g: usize, now: u64) -> bool {
+        let last = self.last[g].load(Ordering::Relaxed);
+        (last == NEVER || now.wrapping_sub(last) >= self.nanos)
+            && self.last[g].compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+    }
+}
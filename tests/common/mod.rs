@@ -0,0 +1,39 @@
+#![allow(dead_code)]
+
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ipcbus::{Bus, OwnedBus};
+
+/// Bus name unique to this test process and call site.
+pub fn unique_name(tag: &str) -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    format!("ipcbus-test-{}-{}-{}", tag, std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Fresh bus, unlinked (segment and semaphore) when dropped.
+pub struct TestBus {
+    pub name: String,
+    bus: OwnedBus,
+}
+
+impl TestBus {
+    pub fn new(tag: &str) -> Self {
+        let name = unique_name(tag);
+        let bus = Bus::create_exclusive(&name).unwrap();
+        Self { name, bus }
+    }
+
+    /// Second handle on the same bus, with consumer state of its own. It shares this
+    /// process's mapping; spawn a process to get an independent one.
+    pub fn attach(&self) -> Bus {
+        Bus::open_or_create(&self.name)
+    }
+}
+
+impl Deref for TestBus {
+    type Target = Bus;
+    fn deref(&self) -> &Bus {
+        &self.bus
+    }
+}
@@ -0,0 +1,259 @@
+mod common;
+
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common::{unique_name, TestBus};
+use ipcbus::{seq_newer, Bus, BusConfig, Consumer, MockClock, SubscriptionMask, SLOTS_PER_TOPIC};
+
+const A: usize = 21;
+const B: usize = 22;
+
+/// Poll once and return what was delivered, as (topic, seq).
+fn poll(consumer: &mut Consumer) -> Vec<(usize, u64)> {
+    let mut got = Vec::new();
+    assert!(consumer.poll(|t, msg| got.push((t, msg.seq))));
+    got
+}
+
+#[test]
+fn renotify_without_a_write_delivers_nothing() {
+    let bus = TestBus::new("consumer-dup");
+    let mut consumer = Consumer::new(bus.attach(), SubscriptionMask::from_topics(&[A]));
+
+    for seq in 1..=3 {
+        bus.write(A, seq, b"x").unwrap();
+    }
+    bus.notify(A);
+    assert_eq!(poll(&mut consumer), [(A, 1), (A, 2), (A, 3)]);
+
+    bus.notify(A);
+    assert_eq!(poll(&mut consumer), []);
+    assert_eq!(consumer.last_seen(A), 3);
+}
+
+#[test]
+fn interleaved_writes_and_drains_are_at_most_once_and_ordered() {
+    let bus = TestBus::new("consumer-order");
+    let mut consumer = Consumer::new(bus.attach(), SubscriptionMask::from_topics(&[A, B]));
+
+    let mut next = [1u64; 2];
+    let mut delivered: [Vec<u64>; 2] = Default::default();
+    let mut expected = [0usize; 2];
+    // Batch sizes cycle through "fits in the ring", "exactly fills it" and "overruns it".
+    for round in 0..60 {
+        for (i, &topic) in [A, B].iter().enumerate() {
+            let batch = [1, 4, SLOTS_PER_TOPIC, SLOTS_PER_TOPIC + 7][(round + i) % 4];
+            // An overrun loses the oldest messages; the last ring's worth survives.
+            expected[i] += batch.min(SLOTS_PER_TOPIC);
+            for _ in 0..batch {
+                bus.write(topic, next[i], &next[i].to_le_bytes()).unwrap();
+                next[i] += 1;
+            }
+            bus.notify(topic);
+        }
+        // Every other round, notify again before draining; nothing new should appear.
+        if round % 2 == 0 {
+            bus.notify(A);
+        }
+        for (t, seq) in poll(&mut consumer) {
+            delivered[if t == A { 0 } else { 1 }].push(seq);
+        }
+    }
+
+    for (i, seqs) in delivered.iter().enumerate() {
+        assert!(seqs.windows(2).all(|w| seq_newer(w[1], w[0])), "topic {} out of order or duplicated", i);
+        assert_eq!(*seqs.last().unwrap(), next[i] - 1, "topic {} missed its newest message", i);
+        assert_eq!(seqs.len(), expected[i], "topic {} delivered a resident message twice or not at all", i);
+    }
+}
+
+#[test]
+fn drain_collect_returns_the_latest_of_each_pending_topic() {
+    let bus = TestBus::new("consumer-collect");
+    let consumer = bus.attach();
+    bus.write(A, 1, b"a1").unwrap();
+    bus.write(A, 2, b"a2").unwrap();
+    bus.write(B, 7, b"b7").unwrap();
+    bus.notify(B);
+    bus.notify(A);
+
+    let batch = consumer.drain_collect(&SubscriptionMask::from_topics(&[A, B]));
+    let got: Vec<_> = batch.iter().map(|(t, msg)| (*t, msg.seq, msg.payload())).collect();
+    assert_eq!(got, [(A, 2, &b"a2"[..]), (B, 7, &b"b7"[..])]);
+    assert!(consumer.drain_collect(&SubscriptionMask::from_topics(&[A, B])).is_empty());
+}
+
+#[test]
+fn a_post_window_holds_back_posts_until_it_ends_or_a_flush() {
+    let clock = Arc::new(MockClock::new(0));
+    let window = Duration::from_micros(50);
+    let config = BusConfig::default().clock(clock.clone()).post_window(window);
+    let name = unique_name("consumer-window");
+    let producer = Bus::create_exclusive_with(&name, &config).unwrap();
+    let consumer = Bus::try_open(&name, &BusConfig::default()).unwrap();
+    let both = SubscriptionMask::from_topics(&[A, B]);
+
+    producer.notify(A);
+    assert!(consumer.try_wait());
+    consumer.drain_pending_mask(&both, |_| {});
+
+    // Goes pending again inside the window: the bit is set, the post held.
+    producer.notify(A);
+    assert!(!consumer.try_wait());
+    assert!(consumer.any_pending());
+
+    // The first notify after the window sends the held post, and then holds its own.
+    clock.advance(window);
+    producer.notify(B);
+    assert!(consumer.try_wait());
+    assert!(!consumer.try_wait());
+
+    producer.flush_posts();
+    assert!(consumer.try_wait());
+    assert!(!consumer.try_wait());
+}
+
+#[test]
+fn a_held_post_does_not_strand_a_consumer_when_the_producer_goes_quiet() {
+    // The producer's clock never moves, so nothing it does would release the post.
+    let clock = Arc::new(MockClock::new(0));
+    let window = Duration::from_millis(20);
+    let config = BusConfig::default().clock(clock).post_window(window);
+    let name = unique_name("consumer-window-quiet");
+    let producer = Bus::create_exclusive_with(&name, &config).unwrap();
+    let consumer = Bus::try_open(&name, &BusConfig::default()).unwrap();
+    assert_eq!(consumer.header().post_window(), Some(window));
+    let both = SubscriptionMask::from_topics(&[A, B]);
+
+    producer.notify(A);
+    consumer.wait();
+    consumer.drain_pending_mask(&both, |_| {});
+    producer.write(B, 1, b"last").unwrap();
+    producer.notify(B);
+    assert!(!consumer.try_wait(), "the post should be held");
+
+    let start = Instant::now();
+    consumer.wait();
+    assert!(start.elapsed() < Duration::from_secs(1), "wait outlived the window: {:?}", start.elapsed());
+    let got: Vec<_> = consumer.drain_collect(&both).into_iter().map(|(t, msg)| (t, msg.seq)).collect();
+    assert_eq!(got, vec![(B, 1)]);
+
+    // Nothing pending: a timed wait still times out, polling the bits once per window.
+    assert!(!consumer.wait_timeout(3 * window));
+}
+
+#[test]
+fn a_subscription_delivers_on_its_thread_and_stops_when_dropped() {
+    let bus = TestBus::new("consumer-subscribe");
+    let (tx, rx) = mpsc::channel();
+    let subscription = bus.attach().subscribe(SubscriptionMask::from_topics(&[A]), move |t, msg| {
+        let _ = tx.send((t, msg.seq));
+    });
+
+    for seq in 1..=3 {
+        bus.write(A, seq, b"x").unwrap();
+        bus.notify(A);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok((A, seq)));
+    }
+    assert!(!subscription.is_finished());
+
+    let start = Instant::now();
+    drop(subscription);
+    assert!(start.elapsed() < Duration::from_secs(2), "joining took {:?}", start.elapsed());
+    // The closure (and its sender) went with the thread.
+    assert_eq!(rx.recv_timeout(Duration::from_millis(10)), Err(mpsc::RecvTimeoutError::Disconnected));
+}
+
+#[test]
+fn sem_value_counts_the_unconsumed_wakeups() {
+    let bus = TestBus::new("consumer-sem-value");
+    let consumer = bus.attach();
+    let Some(idle) = consumer.sem_value() else { return };
+    assert_eq!(idle, 0);
+
+    bus.notify(A);
+    bus.notify(B);
+    assert_eq!(consumer.sem_value(), Some(2));
+    assert_eq!(consumer.pending_count(), 2);
+
+    assert!(consumer.try_wait());
+    assert_eq!(consumer.sem_value(), Some(1));
+}
+
+#[test]
+fn drain_until_stops_going_round_once_the_deadline_passes() {
+    let bus = TestBus::new("consumer-deadline");
+    let consumer = bus.attach();
+    let both = SubscriptionMask::from_topics(&[A, B]);
+    // Delivering A makes B pending, so there is always another pass to do.
+    let chain = |t: usize, seen: &mut Vec<usize>| {
+        seen.push(t);
+        if t == A {
+            bus.notify(B);
+        }
+    };
+
+    bus.notify(A);
+    let mut seen = Vec::new();
+    assert_eq!(consumer.drain_until(&both, Instant::now(), |t| chain(t, &mut seen)), 1);
+    assert_eq!(seen, [A]);
+    // B was left pending, with a token to wake the next wait for it.
+    assert!(consumer.wait_timeout(Duration::ZERO));
+    consumer.drain_now(&both, |t| seen.push(t));
+    assert_eq!(seen, [A, B]);
+
+    bus.notify(A);
+    let mut seen = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    assert_eq!(consumer.drain_until(&both, deadline, |t| chain(t, &mut seen)), 2);
+    assert_eq!(seen, [A, B]);
+    assert!(!consumer.any_pending());
+}
+
+#[test]
+fn bus_age_measures_on_the_bus_clock() {
+    let clock = Arc::new(MockClock::new(1_000));
+    let config = BusConfig::default().clock(clock.clone());
+    let bus = Bus::create_exclusive_with(&unique_name("consumer-age"), &config).unwrap();
+    bus.write(A, 1, b"x").unwrap();
+
+    clock.advance(Duration::from_millis(3));
+    let msg = bus.read(A, 1).unwrap();
+    assert_eq!(msg.publish_ts_nanos, 1_000);
+    assert_eq!(bus.age(&msg), Duration::from_millis(3));
+}
+
+#[test]
+fn read_blocking_leaves_every_group_its_pending_bit() {
+    let bus = TestBus::new("consumer-read-blocking-groups");
+    let only_a = SubscriptionMask::from_topics(&[A]);
+    let group = Bus::open_or_create_group(&bus.name, 1, &only_a);
+
+    bus.write(A, 1, b"reply").unwrap();
+    bus.notify(A);
+    for handle in [&*bus, &group] {
+        let reply = handle.read_blocking(A, 1, Duration::from_millis(100)).unwrap();
+        assert_eq!(reply.payload(), b"reply");
+    }
+
+    for handle in [&*bus, &group] {
+        assert!(handle.try_wait());
+        assert_eq!(handle.pending_topics(&only_a).collect::<Vec<_>>(), [A]);
+    }
+}
+
+#[test]
+fn masks_compare_by_topics_whatever_their_length() {
+    let mut mask = SubscriptionMask::all_except(&[A]);
+    assert_eq!(mask.words().len(), ipcbus::BITWORDS);
+    for t in mask.clone() {
+        mask.clear(t);
+    }
+    assert_eq!(mask, SubscriptionMask::new());
+    assert!(SubscriptionMask::new().words().is_empty());
+    assert_eq!(SubscriptionMask::from_topics(&[A]).words().len(), 1);
+    let wide = SubscriptionMask::from_topics(&[A]).union(&SubscriptionMask::from_topics(&[599]));
+    assert_eq!(wide.words().len(), ipcbus::BITWORDS);
+}
@@ -0,0 +1,110 @@
+//! Producer and consumer in separate processes, so the segment, the semaphore and
+//! the pending bitmap are really shared across address spaces. The test binary
+//! re-runs itself: each role is an ignored test that only does anything when the
+//! parent passes it a bus name through the environment.
+
+mod common;
+
+use std::env;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use common::TestBus;
+use ipcbus::{Bus, BusError, DrainOutcome, SlotStatus, SubscriptionMask};
+
+const BUS_ENV: &str = "IPCBUS_CROSS_PROCESS_BUS";
+const READY: &str = "IPCBUS_CONSUMER_READY";
+const TOPIC: usize = 11;
+const MESSAGES: u64 = 10_000;
+/// Upper bound on a child's whole run, so a hang fails the test instead of wedging it.
+const DEADLINE: Duration = Duration::from_secs(60);
+
+fn payload_for(seq: u64) -> Vec<u8> {
+    let len = 8 + (seq % 64) as usize;
+    let mut data = seq.to_le_bytes().to_vec();
+    data.resize(len, seq as u8);
+    data
+}
+
+fn spawn_role(role: &str, bus: &str) -> Child {
+    Command::new(env::current_exe().unwrap())
+        .args([role, "--exact", "--ignored", "--nocapture", "--test-threads=1"])
+        .env(BUS_ENV, bus)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap()
+}
+
+#[test]
+fn consumer_process_sees_every_message_in_order() {
+    let bus = TestBus::new("xproc");
+
+    let mut consumer = spawn_role("consumer_role", &bus.name);
+    // The producer may only start once the consumer's ack row exists; before that
+    // nothing holds it back from lapping the ring.
+    let mut lines = BufReader::new(consumer.stdout.take().unwrap()).lines().map_while(Result::ok);
+    assert!(lines.any(|line| line.contains(READY)), "consumer exited before registering");
+
+    let producer = spawn_role("producer_role", &bus.name).wait_with_output().unwrap();
+    assert!(producer.status.success(), "producer failed: {}", String::from_utf8_lossy(&producer.stdout));
+    // Keep reading until the consumer exits, so its last output doesn't hit a closed pipe.
+    let rest: Vec<String> = lines.collect();
+    assert!(consumer.wait().unwrap().success(), "consumer failed: {}", rest.join("\n"));
+    assert_eq!(bus.get_latest_seq(TOPIC), MESSAGES);
+}
+
+/// Publishes 1..=MESSAGES with `try_write`, backing off while the slowest consumer
+/// hasn't acked the slot it would reuse, so none is lost to a lapped ring.
+#[test]
+#[ignore = "run as a child of consumer_process_sees_every_message_in_order"]
+fn producer_role() {
+    let Ok(name) = env::var(BUS_ENV) else { return };
+    let bus = Bus::try_open(&name, &Default::default()).unwrap();
+    let start = Instant::now();
+    for seq in 1..=MESSAGES {
+        loop {
+            match bus.try_write(TOPIC, seq, &payload_for(seq)) {
+                Ok(()) => break,
+                Err(BusError::WouldOverwriteUnacked { .. }) => {
+                    assert!(start.elapsed() < DEADLINE, "consumer stopped acking at seq {}", seq);
+                    std::thread::yield_now();
+                }
+                Err(e) => panic!("write of seq {} failed: {}", seq, e),
+            }
+        }
+        bus.notify(TOPIC);
+    }
+}
+
+/// Sleeps on the semaphore and expects every seq exactly once, in order. A timeout
+/// while the next seq is already resident means a notification was lost.
+#[test]
+#[ignore = "run as a child of consumer_process_sees_every_message_in_order"]
+fn consumer_role() {
+    let Ok(name) = env::var(BUS_ENV) else { return };
+    let bus = Bus::try_open(&name, &Default::default()).unwrap();
+    bus.register_consumer().unwrap();
+    println!("{}", READY);
+
+    let mask = SubscriptionMask::from_topics(&[TOPIC]);
+    let start = Instant::now();
+    let mut next = 1;
+    while next <= MESSAGES {
+        assert!(start.elapsed() < DEADLINE, "stuck waiting for seq {}", next);
+        if bus.wait_and_drain_mask_timeout(&mask, Duration::from_secs(10), |_| {}) == DrainOutcome::TimedOut {
+            assert!(bus.read(TOPIC, next).is_none(), "seq {} was resident but no wakeup arrived", next);
+            continue;
+        }
+        while let Some(msg) = bus.read(TOPIC, next) {
+            assert!(msg.crc_ok(), "torn read at seq {}", next);
+            assert_eq!(msg.payload(), &payload_for(next)[..], "payload doesn't belong to seq {}", next);
+            bus.ack(TOPIC, next);
+            next += 1;
+        }
+        // `next` isn't there yet; if a newer seq already took its slot, it never will be.
+        if let SlotStatus::Overwritten { current_seq } = bus.read_status(TOPIC, next) {
+            panic!("seq {} was lost: overwritten by {}", next, current_seq);
+        }
+    }
+}
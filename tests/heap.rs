@@ -0,0 +1,50 @@
+#![cfg(feature = "testing")]
+
+mod common;
+
+use std::thread;
+use std::time::Duration;
+
+use common::unique_name;
+use ipcbus::{Backing, Bus, BusConfig, BusError, SubscriptionMask};
+
+fn heap() -> BusConfig {
+    BusConfig::default().backing(Backing::Heap)
+}
+
+#[test]
+fn handles_with_one_name_share_the_heap_segment_and_wakeups() {
+    let name = unique_name("heap");
+    let owner = Bus::create_exclusive_with(&name, &heap()).unwrap();
+    let consumer = Bus::try_open(&name, &heap()).unwrap();
+    assert!(!std::path::Path::new("/dev/shm").join(&name).exists());
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            for seq in 1..=3 {
+                owner.write(4, seq, format!("m{}", seq).as_bytes()).unwrap();
+                owner.notify(4);
+            }
+        });
+        let mask = SubscriptionMask::from_topics(&[4]);
+        while consumer.get_latest_seq(4) < 3 {
+            assert!(consumer.wait_timeout(Duration::from_secs(5)), "no wakeup from the heap semaphore");
+            consumer.drain_pending_mask(&mask, |_| {});
+        }
+    });
+    assert_eq!(consumer.read(4, 3).unwrap().payload(), b"m3");
+}
+
+#[test]
+fn dropping_the_owner_forgets_the_name() {
+    let name = unique_name("heap");
+    let owner = Bus::create_exclusive_with(&name, &heap()).unwrap();
+    assert!(matches!(Bus::create_exclusive_with(&name, &heap()), Err(BusError::AlreadyExists)));
+    let attached = Bus::try_open(&name, &heap()).unwrap();
+    drop(owner);
+
+    assert!(matches!(Bus::try_open(&name, &heap()), Err(BusError::NotFound)));
+    // The remaining handle keeps the segment alive.
+    attached.write(1, 1, b"still mapped").unwrap();
+    assert_eq!(attached.read(1, 1).unwrap().payload(), b"still mapped");
+}
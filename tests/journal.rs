@@ -0,0 +1,327 @@
+mod common;
+
+use std::panic;
+
+use common::{unique_name, TestBus};
+use ipcbus::{
+    Bus, BusConfig, BusError, Consumer, Journal, Message, Producer, SizedBus, SubscriptionMask, BITWORDS, EMPTY_SEQ,
+    NUM_CHANNELS, NUM_TOPICS, SLOTS_PER_TOPIC,
+};
+
+#[test]
+fn fresh_bus_has_no_resident_messages() {
+    let bus = TestBus::new("fresh");
+    for t in 0..NUM_TOPICS {
+        assert!(bus.read_latest(t).is_none(), "topic {} reported data", t);
+        assert!(bus.read(t, EMPTY_SEQ).is_none());
+        assert_eq!(bus.get_latest_seq(t), EMPTY_SEQ);
+    }
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn an_out_of_range_topic_panics_on_write() {
+    let bus = TestBus::new("bounds");
+    let _ = bus.write(NUM_TOPICS, 1, b"x");
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn an_out_of_range_topic_panics_on_notify() {
+    TestBus::new("bounds").notify(NUM_TOPICS + 64);
+}
+
+#[test]
+fn an_in_place_write_is_invisible_until_committed() {
+    let bus = TestBus::new("in-place");
+    bus.write(5, 1, b"old").unwrap();
+
+    let mut guard = bus.begin_write(5, 1 + bus.depth(5) as u64);
+    guard.write(0, b"fresh");
+    guard.set_msg_type(9);
+    assert!(bus.read(5, 1).is_none(), "slot should be mid-write");
+    guard.commit(5);
+    let msg = bus.read_latest(5).unwrap();
+    assert_eq!((msg.seq, msg.payload(), msg.msg_type), (1 + bus.depth(5) as u64, &b"fresh"[..], 9));
+    assert!(msg.crc_ok());
+
+    // Abandoned: the overwritten message doesn't come back, nor does a half-write show.
+    let mut guard = bus.begin_write(5, 1);
+    guard.write(0, b"x");
+    drop(guard);
+    assert!(bus.read_latest(5).is_none());
+}
+
+#[test]
+#[should_panic(expected = "already has a WriteGuard open")]
+fn a_topic_takes_one_in_place_write_at_a_time() {
+    let bus = TestBus::new("in-place-twice");
+    let _first = bus.begin_write(5, 1);
+    let _second = bus.begin_write(5, 1 + bus.depth(5) as u64);
+}
+
+#[test]
+fn a_dropped_guard_frees_its_topic_for_the_next() {
+    let bus = TestBus::new("in-place-again");
+    drop(bus.begin_write(5, 1));
+    let mut guard = bus.begin_write(5, 2);
+    guard.write(3, b"tail");
+    assert_eq!(guard.capacity(), bus.capacity(5));
+    guard.commit(7);
+    assert_eq!(&bus.read(5, 2).unwrap().payload()[3..], b"tail");
+}
+
+#[test]
+fn opens_of_one_name_in_a_process_share_one_mapping() {
+    let name = unique_name("shared");
+    let first = Bus::open_or_create(&name);
+    let second = Bus::open_or_create(&name);
+    assert_eq!(first.journal, second.journal);
+    assert!(matches!(Bus::create_exclusive(&name), Err(BusError::AlreadyExists)));
+
+    // The creator going away leaves the segment mapped and named for the others.
+    drop(first);
+    second.write(1, 1, b"kept").unwrap();
+    let third = Bus::open(&name).unwrap();
+    assert_eq!(third.journal, second.journal);
+    drop(second);
+    drop(third);
+    assert!(matches!(Bus::open(&name), Err(BusError::NotFound)));
+}
+
+#[test]
+fn a_replaced_segment_gets_a_mapping_of_its_own() {
+    let name = unique_name("shared");
+    let owner = Bus::create_exclusive(&name).unwrap();
+    let mut consumer = Bus::open(&name).unwrap();
+    drop(owner);
+
+    let restarted = Bus::create_exclusive(&name).unwrap();
+    assert_ne!(restarted.journal, consumer.journal);
+    consumer.reopen().unwrap();
+    assert_eq!(restarted.journal, consumer.journal);
+    // Dropping the old mapping must not unlink its successor's name.
+    assert_eq!(Bus::open(&name).unwrap().journal, restarted.journal);
+}
+
+#[cfg(unix)]
+#[test]
+fn a_fixed_address_mapping_lands_there_again_on_reopen() {
+    // Well below where the kernel hands out mappings, so parallel tests don't take it.
+    let addr = Bus::create_exclusive(&unique_name("probe")).unwrap().journal as usize - (1 << 36);
+    let config = BusConfig::default().fixed_addr(Some(addr));
+    let name = unique_name("fixed");
+    let owner = Bus::create_exclusive_with(&name, &config).unwrap();
+    let mut consumer = Bus::try_open(&name, &config).unwrap();
+    assert_eq!(consumer.journal as usize, addr);
+    assert!(matches!(
+        Bus::try_open_or_create_with(&unique_name("fixed"), &config),
+        Err(BusError::Open(_))
+    ));
+
+    drop(owner);
+    let restarted = Bus::create_exclusive(&name).unwrap();
+    restarted.write(3, 1, b"after restart").unwrap();
+    consumer.reopen().unwrap();
+    assert_eq!(consumer.journal as usize, addr);
+    assert_eq!(consumer.read(3, 1).unwrap().payload(), b"after restart");
+}
+
+#[test]
+fn producers_on_disjoint_topic_ranges_never_touch_each_others_topics() {
+    const PRODUCERS: usize = 5;
+    const ROUNDS: u64 = 100;
+    let bus = TestBus::new("multi-producer");
+    let per = NUM_TOPICS / PRODUCERS;
+    let payload = |t: usize, seq: u64| [(t as u64).to_le_bytes(), seq.to_le_bytes()].concat();
+
+    // Adjacent ranges meet inside a pending word, so the bitmap is shared too.
+    std::thread::scope(|s| {
+        for p in 0..PRODUCERS {
+            let producer = bus.attach();
+            s.spawn(move || {
+                for seq in 1..=ROUNDS {
+                    for t in p * per..(p + 1) * per {
+                        producer.write(t, seq, &payload(t, seq)).unwrap();
+                        producer.notify(t);
+                    }
+                }
+            });
+        }
+    });
+
+    let stats = bus.stats();
+    for t in 0..NUM_TOPICS {
+        assert_eq!(bus.get_latest_seq(t), ROUNDS, "topic {}", t);
+        let oldest = ROUNDS + 1 - (bus.depth(t) as u64).min(ROUNDS);
+        for seq in oldest..=ROUNDS {
+            assert_eq!(bus.read(t, seq).unwrap().payload(), payload(t, seq), "topic {} seq {}", t, seq);
+        }
+        assert_eq!((stats.writes[t], stats.notifies[t]), (ROUNDS, ROUNDS), "topic {}", t);
+        assert!(!bus.is_stale(t, std::time::Duration::from_secs(60)), "topic {}", t);
+    }
+    assert!(bus.active_topics().eq(0..NUM_TOPICS));
+    // Nothing drained during the run, so every notify after a topic's first found it pending.
+    assert_eq!(stats.posts_suppressed, NUM_TOPICS as u64 * (ROUNDS - 1));
+    let mut drained = Vec::new();
+    bus.drain_now(&ipcbus::SubscriptionMask::all(), |t| drained.push(t));
+    assert_eq!(drained, (0..NUM_TOPICS).collect::<Vec<_>>());
+    assert_eq!(bus.stats().drain_events, NUM_TOPICS as u64);
+}
+
+#[test]
+fn the_schema_version_travels_with_the_message() {
+    let bus = TestBus::new("schema");
+    bus.write(2, 1, b"old").unwrap();
+    bus.write_versioned(2, 2, 7, 3, b"new").unwrap();
+
+    assert_eq!(bus.read(2, 1).unwrap().schema_version, 0);
+    let msg = bus.read(2, 2).unwrap();
+    assert_eq!((msg.msg_type, msg.schema_version, msg.payload()), (7, 3, &b"new"[..]));
+    assert!(msg.crc_ok());
+}
+
+#[test]
+fn read_into_reuses_one_buffer_across_payload_sizes() {
+    let bus = TestBus::new("read-into");
+    bus.write(3, 1, &[0xAB; 200]).unwrap();
+    bus.write(3, 2, b"short").unwrap();
+
+    let mut buf = Message::default();
+    assert!(bus.read_into(3, 1, &mut buf));
+    assert_eq!(buf, bus.read(3, 1).unwrap());
+    assert!(bus.read_into(3, 2, &mut buf));
+    assert_eq!((buf.seq, buf.payload()), (2, &b"short"[..]));
+    assert!(buf.crc_ok());
+    assert!(!bus.read_into(3, 3, &mut buf));
+    assert!(!bus.read_into(3, EMPTY_SEQ, &mut buf));
+}
+
+#[test]
+fn header_records_the_slots_actually_laid_out() {
+    let config = BusConfig::default().channel_slots(0, 4).coalesce(Bus::topic_id(0, 0, 0, 1));
+    let bus = Bus::create_exclusive_with(&unique_name("total-slots"), &config).unwrap();
+
+    let per_channel = NUM_TOPICS / NUM_CHANNELS;
+    let expected = per_channel * 4 + per_channel * SLOTS_PER_TOPIC - (SLOTS_PER_TOPIC - 1);
+    assert_eq!(bus.header().total_slots as usize, expected);
+    assert_eq!(bus.header().channel_slots, [4, SLOTS_PER_TOPIC as u32]);
+}
+
+#[test]
+fn a_bus_with_fewer_topics_walks_only_its_own_words() {
+    let name = unique_name("few-topics");
+    let bus = Bus::create_exclusive_with(&name, &BusConfig::default().num_topics(100)).unwrap();
+    let attacher = Bus::try_open(&name, &BusConfig::default()).unwrap();
+    assert_eq!((bus.num_topics(), attacher.num_topics()), (100, 100));
+
+    for t in [5, 99] {
+        bus.write(t, 1, b"x").unwrap();
+        bus.notify(t);
+    }
+    assert_eq!(attacher.pending_snapshot().len(), 2);
+    let mut words = [0u64; BITWORDS];
+    assert_eq!(attacher.pending_snapshot_into(&mut words), 2);
+    assert_eq!(words[..2], [1 << 5, 1 << 35]);
+
+    assert_eq!(attacher.snapshot(&SubscriptionMask::all()).len(), 2);
+    assert_eq!(attacher.pending_topics(&SubscriptionMask::from_topics(&[99])).collect::<Vec<_>>(), [99]);
+    let mut drained = Vec::new();
+    attacher.drain_round_robin(&SubscriptionMask::all(), |t| drained.push(t));
+    assert_eq!(drained, [5, 99]);
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn a_topic_past_the_segments_count_panics() {
+    let bus = Bus::create_exclusive_with(&unique_name("few-topics-panic"), &BusConfig::default().num_topics(100)).unwrap();
+    let _ = bus.write(100, 1, b"x");
+}
+
+#[test]
+fn every_per_topic_entry_point_checks_the_segments_count() {
+    let config = BusConfig::default().num_topics(100);
+    let bus = Bus::create_exclusive_with(&unique_name("few-topics-entry"), &config).unwrap();
+    let calls: [(&str, &dyn Fn()); 8] = [
+        ("read_bounded", &|| { let _ = bus.read_bounded(100, 1, 4); }),
+        ("read_ref", &|| { let _ = bus.read_ref(100, 1); }),
+        ("read_status", &|| { let _ = bus.read_status(100, 1); }),
+        ("read_oldest", &|| { let _ = bus.read_oldest(100); }),
+        ("try_write", &|| { let _ = bus.try_write(100, 1, b"x"); }),
+        ("write_with_overflow", &|| { let _ = bus.write_with_overflow(100, 1, b"x", |_, _| {}); }),
+        ("write_truncating", &|| { let _ = bus.write_truncating(100, 1, b"x"); }),
+        ("write_batch", &|| { let _ = bus.write_batch(&[(3, 1, b"x"), (100, 1, b"x")]); }),
+    ];
+    for (name, call) in calls {
+        assert!(panic::catch_unwind(panic::AssertUnwindSafe(call)).is_err(), "{} took topic 100", name);
+    }
+    assert!(bus.read(3, 1).is_none(), "write_batch wrote before rejecting the batch");
+}
+
+/// 64 topics, rings of at most 4 slots, payloads of at most 64 bytes, one bitmap word.
+type TinyBus = SizedBus<64, 4, 64, 1>;
+
+#[test]
+fn a_sized_bus_caps_the_config_to_its_dimensions() {
+    assert!(core::mem::size_of::<Journal<64, 1>>() < core::mem::size_of::<Journal>());
+    let bus = TinyBus::create_exclusive(&unique_name("sized")).unwrap();
+    assert_eq!((bus.num_topics(), bus.depth(3), bus.capacity(3)), (64, 4, 64));
+    assert_eq!(bus.header().topic_capacity, 64);
+
+    for seq in 1..=5 {
+        bus.write(3, seq, b"tick").unwrap();
+    }
+    bus.notify(3);
+    assert_eq!(bus.read_oldest(3).unwrap().seq, 2);
+    assert_eq!(bus.write(3, 6, &[0; 65]), Err(BusError::PayloadTooLarge { len: 65, max: 64 }));
+
+    let mut drained = Vec::new();
+    bus.drain_pending_mask(&SubscriptionMask::all(), |t| drained.push(t));
+    assert_eq!(drained, [3]);
+}
+
+#[test]
+fn only_a_bus_of_the_creators_dimensions_attaches() {
+    let name = unique_name("sized-attach");
+    let bus = TinyBus::create_exclusive(&name).unwrap();
+    bus.write(7, 1, b"x").unwrap();
+
+    assert_eq!(TinyBus::open(&name).unwrap().read_latest(7).unwrap().payload(), b"x");
+    assert!(matches!(Bus::open(&name), Err(BusError::IncompatibleLayout { .. })));
+    assert!(matches!(SizedBus::<128, 4, 64, 2>::open(&name), Err(BusError::IncompatibleLayout { .. })));
+    // Same journal, but the creator's 4-slot rings are deeper than 2 allows.
+    assert!(matches!(SizedBus::<64, 2, 64, 1>::open(&name), Err(BusError::IncompatibleLayout { .. })));
+}
+
+#[test]
+fn producers_consumers_and_views_run_on_a_sized_bus() {
+    let name = unique_name("sized-endpoints");
+    let bus = TinyBus::create_exclusive(&name).unwrap();
+    let mut producer = Producer::exclusive(TinyBus::open(&name).unwrap(), 9).unwrap();
+    let mut consumer = Consumer::new(TinyBus::open(&name).unwrap(), SubscriptionMask::from_topics(&[9]));
+
+    for payload in [b"a", b"b", b"c"] {
+        producer.publish(payload).unwrap();
+    }
+    let mut got = Vec::new();
+    assert!(consumer.poll(|t, msg| got.push((t, msg.seq))));
+    assert_eq!(got, [(9, 1), (9, 2), (9, 3)]);
+
+    assert_eq!(bus.topic(9).latest().unwrap().seq, 3);
+    assert!(bus.claim_producer(9).is_err());
+    let seq = bus.write_large(10, 1, &[7; 100]).unwrap();
+    assert_eq!(bus.read_large(10, seq).unwrap(), [7; 100]);
+    let monitor = TinyBus::open_readonly(&name).unwrap();
+    assert_eq!(monitor.get_latest_seq(9), 3);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn a_read_only_handle_refuses_the_futex_backend() {
+    let config = BusConfig::default().notify_backend(ipcbus::NotifyBackend::Futex);
+    let name = unique_name("readonly-futex");
+    let bus = Bus::create_exclusive_with(&name, &config).unwrap();
+    bus.write(3, 1, b"x").unwrap();
+    assert!(matches!(Bus::open_readonly_with(&name, &config), Err(BusError::Notifier(_))));
+    assert_eq!(Bus::open_readonly(&name).unwrap().get_latest_seq(3), 1);
+}
@@ -0,0 +1,30 @@
+mod common;
+
+use common::{unique_name, TestBus};
+use ipcbus::{Bus, BusConfig, BusError};
+
+const TOPIC: usize = 11;
+
+#[test]
+fn large_messages_span_the_seq_wrap() {
+    let bus = TestBus::new("large-wrap");
+    let data: Vec<u8> = (0..bus.capacity(TOPIC) * 3).map(|i| i as u8).collect();
+
+    let last = bus.write_large(TOPIC, u64::MAX - 1, &data).unwrap();
+    assert_eq!(last, 2);
+    for seq in [u64::MAX - 1, u64::MAX, 1, 2] {
+        assert_eq!(bus.read_large(TOPIC, seq).as_deref(), Some(&data[..]));
+    }
+}
+
+#[test]
+fn a_channel_too_small_for_a_fragment_header_rejects_large_writes() {
+    let topic = Bus::topic_id(0, 0, 0, 1);
+    let config = BusConfig::default().channel_data_size(1, 4);
+    let bus = Bus::create_exclusive_with(&unique_name("large-tiny"), &config).unwrap();
+
+    assert_eq!(bus.max_large_payload(topic), 0);
+    assert!(matches!(bus.write_large(topic, 1, &[]), Err(BusError::PayloadTooLarge { len: 0, max: 0 })));
+    assert!(matches!(bus.write_large(topic, 1, &[7]), Err(BusError::PayloadTooLarge { len: 1, max: 0 })));
+    assert!(bus.read(topic, 1).is_none());
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::{unique_name, TestBus};
+use ipcbus::{Bus, BusConfig, Player, Recorder, SubscriptionMask};
+
+const TOPIC: usize = 11;
+
+fn recording_of(bus: &Bus) -> Vec<u8> {
+    let mut recorder = Recorder::new(Vec::new(), bus).unwrap();
+    bus.write(TOPIC, 1, b"tick").unwrap();
+    bus.notify(TOPIC);
+    assert_eq!(recorder.record_drain(bus, &SubscriptionMask::from_topics(&[TOPIC])).unwrap(), 1);
+    recorder.into_inner()
+}
+
+#[test]
+fn a_recording_replays_onto_a_bus_with_the_same_layout() {
+    let source = TestBus::new("record-source");
+    let target = TestBus::new("record-target");
+
+    let mut player = Player::new(Cursor::new(recording_of(&source))).unwrap();
+    assert!(player.header().matches(target.header()));
+    assert_eq!(player.replay(&target, false).unwrap(), 1);
+    assert_eq!(target.read(TOPIC, 1).unwrap().payload(), b"tick");
+}
+
+#[test]
+fn a_drain_records_every_seq_written_since_the_last_one() {
+    let source = TestBus::new("record-burst");
+    let target = TestBus::new("record-burst-target");
+    let mask = SubscriptionMask::from_topics(&[TOPIC]);
+    assert!(source.depth(TOPIC) >= 4);
+
+    let mut recorder = Recorder::new(Vec::new(), &source).unwrap();
+    for seq in 1..=3 {
+        source.write(TOPIC, seq, format!("tick {}", seq).as_bytes()).unwrap();
+    }
+    source.notify(TOPIC);
+    assert_eq!(recorder.record_drain(&source, &mask).unwrap(), 3);
+    // Only what is new since then goes into the next drain.
+    source.write(TOPIC, 4, b"tick 4").unwrap();
+    source.notify(TOPIC);
+    assert_eq!(recorder.record_drain(&source, &mask).unwrap(), 1);
+
+    let mut player = Player::new(Cursor::new(recorder.into_inner())).unwrap();
+    assert_eq!(player.replay(&target, false).unwrap(), 4);
+    for seq in 1..=4 {
+        assert_eq!(target.read(TOPIC, seq).unwrap().payload(), format!("tick {}", seq).as_bytes());
+    }
+}
+
+#[test]
+fn replay_refuses_a_bus_with_other_per_channel_config_before_writing() {
+    let source = TestBus::new("record-default");
+    let recording = recording_of(&source);
+
+    for config in [
+        BusConfig::default().channel_data_size(0, 256),
+        BusConfig::default().channel_slots(0, 4),
+        BusConfig::default().payload_align(64),
+        BusConfig::default().coalesce(TOPIC),
+    ] {
+        let target = Bus::create_exclusive_with(&unique_name("record-other"), &config).unwrap();
+        let mut player = Player::new(Cursor::new(&recording)).unwrap();
+        assert!(!player.header().matches(target.header()));
+        assert!(player.replay(&target, false).is_err());
+        assert!(target.read(TOPIC, 1).is_none());
+    }
+}
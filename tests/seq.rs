@@ -0,0 +1,171 @@
+mod common;
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+
+use common::TestBus;
+use ipcbus::{seq_newer, BusError, Producer, ReadOutcome, EMPTY_SEQ, SEQLOCK_RETRIES, SLOTS_PER_TOPIC};
+
+const TOPIC: usize = 11;
+
+#[test]
+fn newer_is_plain_order_away_from_the_wrap() {
+    assert!(seq_newer(2, 1));
+    assert!(!seq_newer(1, 2));
+    assert!(!seq_newer(5, 5));
+}
+
+#[test]
+fn newer_straddles_u64_max() {
+    assert!(seq_newer(0, u64::MAX));
+    assert!(seq_newer(1, u64::MAX));
+    assert!(seq_newer(3, u64::MAX - 2));
+    assert!(!seq_newer(u64::MAX, 1));
+    assert!(!seq_newer(u64::MAX - 2, 3));
+}
+
+#[test]
+fn newer_is_antisymmetric_across_half_the_range() {
+    let b = u64::MAX - 10;
+    for step in [1, 1 << 20, (1 << 63) - 1] {
+        let a = b.wrapping_add(step);
+        assert!(seq_newer(a, b), "{} steps ahead", step);
+        assert!(!seq_newer(b, a), "{} steps behind", step);
+    }
+}
+
+#[test]
+fn latest_seq_follows_the_wrap() {
+    let bus = TestBus::new("seq-latest");
+    for seq in [u64::MAX - 1, u64::MAX, 1, 2] {
+        bus.write(TOPIC, seq, &seq.to_le_bytes()).unwrap();
+    }
+    assert_eq!(bus.get_latest_seq(TOPIC), 2);
+    assert_eq!(bus.read_latest(TOPIC).unwrap().payload(), &2u64.to_le_bytes());
+}
+
+#[test]
+fn read_since_orders_across_the_wrap() {
+    let bus = TestBus::new("seq-since");
+    for seq in [u64::MAX - 1, u64::MAX, 1, 2] {
+        bus.write(TOPIC, seq, &seq.to_le_bytes()).unwrap();
+    }
+    let seqs = |after| bus.topic(TOPIC).read_since(after).iter().map(|m| m.seq).collect::<Vec<_>>();
+    assert_eq!(seqs(u64::MAX - 1), [u64::MAX, 1, 2]);
+    assert_eq!(seqs(u64::MAX), [1, 2]);
+    assert_eq!(seqs(2), [] as [u64; 0]);
+    assert_eq!(seqs(EMPTY_SEQ), [u64::MAX - 1, u64::MAX, 1, 2]);
+}
+
+/// A reader chasing a producer that laps the ring: whenever a published seq reads
+/// as missing, it is gone from every slot, not parked in a neighbouring one.
+#[test]
+fn a_missed_seq_is_in_no_other_slot() {
+    let bus = TestBus::new("seq-missed");
+    let reader = bus.attach();
+    let published = AtomicU64::new(0);
+    let done = AtomicBool::new(false);
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            for seq in 1..=100_000u64 {
+                bus.write(TOPIC, seq, &seq.to_le_bytes()).unwrap();
+                published.store(seq, Ordering::Release);
+            }
+            done.store(true, Ordering::Release);
+        });
+
+        while !done.load(Ordering::Acquire) {
+            // The oldest seq the ring still held at the load: the next write recycles its slot.
+            let seq = published.load(Ordering::Acquire).saturating_sub(SLOTS_PER_TOPIC as u64 - 1);
+            if seq == EMPTY_SEQ {
+                continue;
+            }
+            match reader.read_bounded(TOPIC, seq, SEQLOCK_RETRIES) {
+                ReadOutcome::Read { msg, .. } => assert_eq!(msg.payload(), &seq.to_le_bytes()),
+                ReadOutcome::Missing => {
+                    // Seqs only move forward, so once overwritten it can't come back.
+                    let resident: Vec<u64> = reader.topic(TOPIC).read_since(seq - 1).iter().map(|m| m.seq).collect();
+                    assert!(!resident.contains(&seq), "missed seq {} found in another slot", seq);
+                }
+                ReadOutcome::Contended => {}
+            }
+        }
+    });
+}
+
+#[test]
+fn a_producer_refuses_a_non_contiguous_seq_unless_asked_to_jump() {
+    let bus = TestBus::new("seq");
+    let mut producer = Producer::new(bus.attach(), TOPIC);
+    assert_eq!(producer.publish_seq(1, b"a"), Ok(1));
+    assert_eq!(
+        producer.publish_seq(3, b"c"),
+        Err(BusError::NonContiguousSeq { topic_id: TOPIC, expected: 2, got: 3 })
+    );
+    assert!(bus.read(TOPIC, 3).is_none());
+    assert_eq!(producer.seq(), 2);
+
+    assert_eq!(producer.publish_at(10, b"j"), Ok(10));
+    assert_eq!(producer.publish(b"k"), Ok(11));
+    assert_eq!(producer.publish_seq(12, b"l"), Ok(12));
+    assert_eq!(bus.get_latest_seq(TOPIC), 12);
+}
+
+#[test]
+fn claimed_seqs_are_a_block_that_publish_continues_after() {
+    let bus = TestBus::new("seq");
+    let mut producer = Producer::new(bus.attach(), TOPIC);
+    assert_eq!(producer.publish(b"a"), Ok(1));
+
+    let block = producer.claim_seqs(3);
+    assert_eq!(block, 2..5);
+    let items: Vec<(usize, u64, &[u8])> = block.map(|seq| (TOPIC, seq, &b"batched"[..])).collect();
+    bus.write_batch(&items).unwrap();
+    assert_eq!(producer.publish_seq(5, b"e"), Ok(5));
+
+    assert_eq!(producer.claim_seqs(0), 6..6);
+    assert_eq!(producer.seq(), 6);
+    assert_eq!(bus.read(TOPIC, 4).unwrap().payload(), b"batched");
+}
+
+#[test]
+fn every_write_path_rejects_the_reserved_seq() {
+    let bus = TestBus::new("seq");
+    assert_eq!(bus.write(TOPIC, EMPTY_SEQ, b"x"), Err(BusError::ReservedSeq));
+    assert_eq!(bus.try_write(TOPIC, EMPTY_SEQ, b"x"), Err(BusError::ReservedSeq));
+    assert_eq!(bus.write_truncating(TOPIC, EMPTY_SEQ, b"x"), Err(BusError::ReservedSeq));
+    assert_eq!(bus.topic(TOPIC).write(EMPTY_SEQ, b"x"), Err(BusError::ReservedSeq));
+
+    // One bad seq keeps the whole batch out, notifies included.
+    let batch: [(usize, u64, &[u8]); 2] = [(TOPIC, 1, b"a"), (TOPIC + 1, EMPTY_SEQ, b"b")];
+    assert_eq!(bus.write_batch(&batch), Err(BusError::ReservedSeq));
+    assert_eq!(bus.get_latest_seq(TOPIC), EMPTY_SEQ);
+    assert!(!bus.any_pending());
+    assert_eq!(bus.stats().total_writes(), 0);
+}
+
+#[test]
+fn ack_watermarks_follow_the_wrap() {
+    let bus = TestBus::new("seq");
+    let consumer = bus.attach();
+    bus.write(TOPIC, u64::MAX - 2, b"w").unwrap();
+    consumer.register_consumer().unwrap();
+    // The numbering wraps past EMPTY_SEQ: MAX - 1, MAX, 1, 2.
+    for seq in [u64::MAX - 1, u64::MAX, 1, 2] {
+        bus.write(TOPIC, seq, b"w").unwrap();
+    }
+    assert_eq!(bus.topic_lag(TOPIC), 4);
+    assert_eq!(bus.oldest_unacked(TOPIC), Some(u64::MAX - 1));
+
+    consumer.ack(TOPIC, u64::MAX);
+    assert_eq!(bus.oldest_unacked(TOPIC), Some(1));
+    assert_eq!(bus.topic_lag(TOPIC), 2);
+    // A late ack doesn't move the watermark back across the wrap.
+    consumer.ack(TOPIC, u64::MAX - 1);
+    assert_eq!(bus.reclaim_watermark(TOPIC), Some(u64::MAX));
+
+    consumer.ack(TOPIC, 2);
+    assert_eq!(bus.oldest_unacked(TOPIC), None);
+    assert_eq!(bus.topic_lag(TOPIC), 0);
+}
@@ -0,0 +1,19 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use common::TestBus;
+
+#[test]
+fn message_round_trips_through_bincode() {
+    let bus = TestBus::new("serde");
+    bus.write(12, 3, b"bid=101.5 ask=101.6").unwrap();
+    let msg = bus.read(12, 3).unwrap();
+
+    let bytes = bincode::serialize(&msg).unwrap();
+    // Only the payload is encoded, not the whole DATA_SIZE buffer.
+    assert!(bytes.len() < 64);
+    let back: ipcbus::Message = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(back, msg);
+    assert!(back.crc_ok());
+}
@@ -0,0 +1,20 @@
+use ipcbus::{Bus, NUM_CHANNELS, NUM_EXCHANGES, NUM_MARKETS, NUM_SYMBOLS, NUM_TOPICS};
+
+#[test]
+fn topic_id_round_trips_every_topic() {
+    let mut seen = vec![false; NUM_TOPICS];
+    for exchange in 0..NUM_EXCHANGES {
+        for market in 0..NUM_MARKETS {
+            for symbol in 0..NUM_SYMBOLS {
+                for channel in 0..NUM_CHANNELS {
+                    let t = Bus::topic_id(exchange, market, symbol, channel);
+                    assert!(t < NUM_TOPICS);
+                    assert!(!seen[t], "topic_id {} produced twice", t);
+                    seen[t] = true;
+                    assert_eq!(Bus::topic_coords(t), (exchange, market, symbol, channel));
+                }
+            }
+        }
+    }
+    assert!(seen.iter().all(|&s| s));
+}